@@ -0,0 +1,61 @@
+//! Cost of emitting events on the caller's thread with the CloudWatch layer installed.
+//!
+//! The exporter runs on a background runtime and discards batches, so only the formatting
+//! and the hand-off to the exporter are measured.
+
+use std::time::Duration;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use tracing_cloudwatch::{BoxFuture, CloudWatchClient, ExportConfig, LogBatch, PutLogsError};
+use tracing_subscriber::layer::SubscriberExt;
+
+struct DiscardClient;
+
+impl CloudWatchClient for DiscardClient {
+    fn put_logs<'a>(&'a self, _: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+fn hot_path(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_time()
+        .build()
+        .unwrap();
+    let _enter = runtime.enter();
+
+    let (cw_layer, _guard) = tracing_cloudwatch::layer().with_client(
+        DiscardClient,
+        ExportConfig::default()
+            .with_batch_size(1000)
+            .with_interval(Duration::from_millis(100))
+            .with_queue_capacity(100_000),
+    );
+    let subscriber = tracing_subscriber::registry().with(cw_layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let mut group = c.benchmark_group("caller_thread");
+    group.bench_function("info", |b| b.iter(|| tracing::info!("request handled")));
+    group.bench_function("info_with_fields", |b| {
+        b.iter(|| {
+            tracing::info!(
+                user_id = 42,
+                path = "/api/orders",
+                latency_ms = 12.5,
+                "request handled"
+            )
+        })
+    });
+    group.bench_function("span_with_event", |b| {
+        b.iter(|| {
+            let span = tracing::info_span!("request", user_id = 42);
+            let _entered = span.enter();
+            tracing::info!("request handled");
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, hot_path);
+criterion_main!(benches);
@@ -0,0 +1,292 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use chrono::TimeDelta;
+
+use crate::{
+    dispatch::LogEvent,
+    export::MAX_BATCH_SIZE,
+    oversized::{EVENT_OVERHEAD, MAX_EVENT_SIZE},
+};
+
+/// The maximum size of a `PutLogEvents` request, counted like [`PendingBatch::bytes`].
+pub(crate) const MAX_BATCH_BYTES: usize = 1_048_576;
+
+/// The maximum time span between the events of a `PutLogEvents` request.
+const MAX_BATCH_SPAN: TimeDelta = TimeDelta::hours(24);
+
+/// The number of leading events of `events`, sorted by timestamp, which fit in one `PutLogEvents`
+/// request: at most [`MAX_BATCH_SIZE`] events and [`MAX_BATCH_BYTES`], spanning at most 24 hours.
+/// At least one event, so that an oversized event is sent and rejected instead of blocking the
+/// following ones.
+pub(crate) fn batch_len(events: &[LogEvent]) -> usize {
+    let Some(first) = events.first() else {
+        return 0;
+    };
+    let mut bytes = 0;
+    events
+        .iter()
+        .take(MAX_BATCH_SIZE)
+        .take_while(|event| {
+            bytes += event.message.len() + EVENT_OVERHEAD;
+            bytes <= MAX_BATCH_BYTES && event.timestamp - first.timestamp <= MAX_BATCH_SPAN
+        })
+        .count()
+        .max(1)
+}
+
+/// The batch waiting to be exported, passed to a [`BatchPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingBatch {
+    pub(crate) events: usize,
+    pub(crate) bytes: usize,
+    pub(crate) age: Duration,
+}
+
+impl PendingBatch {
+    /// The number of events.
+    pub fn events(&self) -> usize {
+        self.events
+    }
+
+    /// The size of the events as computed by CloudWatch: the UTF-8 bytes of each message plus
+    /// 26 bytes per event.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// How long the oldest event has been waiting.
+    pub fn age(&self) -> Duration {
+        self.age
+    }
+}
+
+/// Why a batch was exported, passed to [`BatchPolicy::on_flush`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FlushTrigger {
+    /// [`BatchPolicy::is_full`] returned true.
+    Full,
+    /// The oldest event reached [`BatchPolicy::max_age`].
+    MaxAge,
+    /// An event reached the level of [`ExportConfig::with_flush_level`](crate::ExportConfig::with_flush_level).
+    FlushLevel,
+    /// The configuration was reloaded.
+    Reload,
+    /// The exporter is shutting down.
+    Shutdown,
+}
+
+/// Decides when the exporter sends the events waiting in its batch, set with
+/// [`ExportConfig::with_batch_policy`](crate::ExportConfig::with_batch_policy).
+///
+/// The exporter only wakes up for new events and for the deadline given by [`Self::max_age`],
+/// so an empty batch costs nothing.
+pub trait BatchPolicy: Send + Sync {
+    /// Whether the batch should be exported now. Called after each event added to it.
+    fn is_full(&self, batch: &PendingBatch) -> bool;
+
+    /// How long the oldest event may wait before the batch is exported.
+    /// `None` waits until the batch is full or the exporter shuts down.
+    fn max_age(&self) -> Option<Duration>;
+
+    /// Called after a batch was exported, e.g. to adapt the next batch to the load.
+    fn on_flush(&self, batch: &PendingBatch, trigger: FlushTrigger) {
+        let _ = (batch, trigger);
+    }
+}
+
+/// Shares a policy with the exporter, e.g. to read [`AdaptivePolicy::target`].
+impl<P: BatchPolicy + ?Sized> BatchPolicy for Arc<P> {
+    fn is_full(&self, batch: &PendingBatch) -> bool {
+        (**self).is_full(batch)
+    }
+
+    fn max_age(&self) -> Option<Duration> {
+        (**self).max_age()
+    }
+
+    fn on_flush(&self, batch: &PendingBatch, trigger: FlushTrigger) {
+        (**self).on_flush(batch, trigger)
+    }
+}
+
+/// [`BatchPolicy`] exporting the batch once any of its thresholds is reached.
+///
+/// Without thresholds, batches are only exported on shutdown.
+/// [`ExportConfig::with_batch_size`](crate::ExportConfig::with_batch_size) and
+/// [`with_interval`](crate::ExportConfig::with_interval) configure the default policy,
+/// a `ThresholdPolicy` with `max_events` and `max_age`.
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// // Latency target of one second, and batches of at most 512 KB.
+/// let policy = tracing_cloudwatch::ThresholdPolicy::new()
+///     .with_max_bytes(512 * 1024)
+///     .with_max_age(Duration::from_secs(1));
+/// let config = tracing_cloudwatch::ExportConfig::default().with_batch_policy(policy);
+/// # let _ = config;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ThresholdPolicy {
+    max_events: Option<usize>,
+    max_bytes: Option<usize>,
+    max_age: Option<Duration>,
+}
+
+impl ThresholdPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Export the batch once it holds `max_events` events.
+    pub fn with_max_events(self, max_events: usize) -> Self {
+        Self {
+            max_events: Some(max_events),
+            ..self
+        }
+    }
+
+    /// Export the batch once its size reaches `max_bytes`, see [`PendingBatch::bytes`].
+    ///
+    /// The batch is checked after each event, so it should leave room for one more event below
+    /// the 1 MB limit of a `PutLogEvents` request.
+    pub fn with_max_bytes(self, max_bytes: usize) -> Self {
+        Self {
+            max_bytes: Some(max_bytes),
+            ..self
+        }
+    }
+
+    /// Export the batch once its oldest event waited `max_age`.
+    pub fn with_max_age(self, max_age: Duration) -> Self {
+        Self {
+            max_age: Some(max_age),
+            ..self
+        }
+    }
+}
+
+impl BatchPolicy for ThresholdPolicy {
+    fn is_full(&self, batch: &PendingBatch) -> bool {
+        self.max_events.is_some_and(|max| batch.events >= max)
+            || self.max_bytes.is_some_and(|max| batch.bytes >= max)
+    }
+
+    fn max_age(&self) -> Option<Duration> {
+        self.max_age
+    }
+}
+
+/// [`BatchPolicy`] growing batches under load and shrinking them when idle.
+///
+/// The batch is exported once it reaches a target number of events, starting at `min_events`.
+/// The target doubles, up to `max_events`, whenever a batch fills up, and halves, down to
+/// `min_events`, whenever `max_age` passes with less than half of it. Batches are also exported
+/// before they could exceed the size limit of a `PutLogEvents` request.
+#[derive(Debug)]
+pub struct AdaptivePolicy {
+    min_events: usize,
+    max_events: usize,
+    max_age: Duration,
+    target: AtomicUsize,
+}
+
+impl AdaptivePolicy {
+    /// `min_events` is raised to 1 and `max_events` is clamped between it and 10000.
+    pub fn new(min_events: usize, max_events: usize, max_age: Duration) -> Self {
+        let min_events = min_events.max(1);
+        let max_events = max_events.clamp(min_events, MAX_BATCH_SIZE.max(min_events));
+        Self {
+            min_events,
+            max_events,
+            max_age,
+            target: AtomicUsize::new(min_events),
+        }
+    }
+
+    /// The number of events the next batch is exported at.
+    pub fn target(&self) -> usize {
+        self.target.load(Ordering::Relaxed)
+    }
+}
+
+impl BatchPolicy for AdaptivePolicy {
+    fn is_full(&self, batch: &PendingBatch) -> bool {
+        batch.events >= self.target() || batch.bytes + MAX_EVENT_SIZE > MAX_BATCH_BYTES
+    }
+
+    fn max_age(&self) -> Option<Duration> {
+        Some(self.max_age)
+    }
+
+    fn on_flush(&self, batch: &PendingBatch, trigger: FlushTrigger) {
+        let target = self.target();
+        let next = match trigger {
+            FlushTrigger::Full => target.saturating_mul(2).min(self.max_events),
+            FlushTrigger::MaxAge if batch.events < target / 2 => (target / 2).max(self.min_events),
+            _ => target,
+        };
+        self.target.store(next, Ordering::Relaxed);
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct SharedBatchPolicy(pub(crate) Arc<dyn BatchPolicy>);
+
+impl std::fmt::Debug for SharedBatchPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedBatchPolicy")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch(events: usize, bytes: usize) -> PendingBatch {
+        PendingBatch {
+            events,
+            bytes,
+            age: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn threshold_policy_flushes_at_any_threshold() {
+        let policy = ThresholdPolicy::new()
+            .with_max_events(10)
+            .with_max_bytes(1000);
+
+        assert!(!policy.is_full(&batch(9, 999)));
+        assert!(policy.is_full(&batch(10, 0)));
+        assert!(policy.is_full(&batch(1, 1000)));
+        assert!(!ThresholdPolicy::new().is_full(&batch(10_000, 1_000_000)));
+    }
+
+    #[test]
+    fn adaptive_policy_follows_load() {
+        let policy = AdaptivePolicy::new(10, 40, Duration::from_secs(1));
+
+        assert!(policy.is_full(&batch(10, 0)));
+        policy.on_flush(&batch(10, 0), FlushTrigger::Full);
+        policy.on_flush(&batch(20, 0), FlushTrigger::Full);
+        policy.on_flush(&batch(40, 0), FlushTrigger::Full);
+        assert_eq!(policy.target(), 40);
+
+        policy.on_flush(&batch(25, 0), FlushTrigger::MaxAge);
+        assert_eq!(policy.target(), 40);
+        policy.on_flush(&batch(3, 0), FlushTrigger::MaxAge);
+        policy.on_flush(&batch(3, 0), FlushTrigger::MaxAge);
+        policy.on_flush(&batch(3, 0), FlushTrigger::MaxAge);
+        assert_eq!(policy.target(), 10);
+
+        assert!(policy.is_full(&batch(1, MAX_BATCH_BYTES - MAX_EVENT_SIZE + 1)));
+    }
+}
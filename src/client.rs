@@ -4,31 +4,149 @@ mod rusoto;
 #[cfg(feature = "awssdk")]
 mod awssdk;
 
-use async_trait::async_trait;
+#[cfg(feature = "sigv4")]
+pub(crate) mod sigv4;
 
-use crate::{dispatch::LogEvent, export::LogDestination};
+pub(crate) mod fallback;
+pub mod middleware;
+pub(crate) mod tee;
+
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use crate::{dispatch::LogEvent, export::LogDestination, oversized::EVENT_OVERHEAD};
+
+/// A boxed future returned by [`CloudWatchClient::put_logs`].
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
 
 /// Trait that abstracts API call using the SDK.
-#[async_trait]
-pub trait CloudWatchClient {
-    async fn put_logs(&self, dest: LogDestination, logs: Vec<LogEvent>)
-    -> Result<(), PutLogsError>;
+///
+/// The trait is dyn compatible, so `Box<dyn CloudWatchClient>` and `Arc<dyn CloudWatchClient>`
+/// are clients as well. Implement it by returning a pinned `async move` block.
+///
+/// ```
+/// use tracing_cloudwatch::{BoxFuture, CloudWatchClient, LogBatch, PutLogsError};
+///
+/// struct StdoutClient;
+///
+/// impl CloudWatchClient for StdoutClient {
+///     fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+///         Box::pin(async move {
+///             for event in batch.events {
+///                 println!("{}", event.message);
+///             }
+///             Ok(())
+///         })
+///     }
+/// }
+/// ```
+pub trait CloudWatchClient: Send + Sync {
+    fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>>;
+
+    /// Create the log group and stream of `destination` unless they already exist.
+    ///
+    /// Called by [`ExportConfig::validate`](crate::ExportConfig::validate).
+    /// The default implementation does nothing.
+    fn ensure_destination<'a>(
+        &'a self,
+        destination: &'a LogDestination,
+    ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        let _ = destination;
+        Box::pin(async { Ok(()) })
+    }
+}
+
+impl<C: CloudWatchClient + ?Sized> CloudWatchClient for Arc<C> {
+    fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        (**self).put_logs(batch)
+    }
+
+    fn ensure_destination<'a>(
+        &'a self,
+        destination: &'a LogDestination,
+    ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        (**self).ensure_destination(destination)
+    }
+}
+
+impl<C: CloudWatchClient + ?Sized> CloudWatchClient for Box<C> {
+    fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        (**self).put_logs(batch)
+    }
+
+    fn ensure_destination<'a>(
+        &'a self,
+        destination: &'a LogDestination,
+    ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        (**self).ensure_destination(destination)
+    }
+}
+
+/// A batch of events borrowed from the exporter, so it can be sent again without cloning.
+#[derive(Debug, Clone, Copy)]
+pub struct LogBatch<'a> {
+    pub destination: &'a LogDestination,
+    pub events: &'a [LogEvent],
+    pub meta: BatchMeta,
+}
+
+/// Metadata about a [`LogBatch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchMeta {
+    /// Number of times this batch has been sent, starting at 1.
+    pub attempt: u32,
+    /// Identifies the batch within the exporter.
+    pub batch_id: u64,
+    /// Size of the batch as computed by CloudWatch, i.e. the messages plus 26 bytes per event.
+    pub byte_size: usize,
+}
+
+impl<'a> LogBatch<'a> {
+    /// Create the first attempt of a batch.
+    pub fn new(destination: &'a LogDestination, events: &'a [LogEvent], batch_id: u64) -> Self {
+        let byte_size = events
+            .iter()
+            .map(|event| event.message.len() + EVENT_OVERHEAD)
+            .sum();
+        Self {
+            destination,
+            events,
+            meta: BatchMeta {
+                attempt: 1,
+                batch_id,
+                byte_size,
+            },
+        }
+    }
+
+    /// The same batch with another attempt number.
+    pub fn with_attempt(self, attempt: u32) -> Self {
+        Self {
+            meta: BatchMeta {
+                attempt,
+                ..self.meta
+            },
+            ..self
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum PutLogsError {
     #[error("{message}")]
     LogDestinationNotFound { message: String },
+    /// CloudWatch rejected the batch itself, e.g. with `InvalidParameterException`.
+    /// Sending the same batch again fails the same way.
+    #[error("{message}")]
+    InvalidRequest { message: String },
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
 pub struct NoopClient {}
 
-#[async_trait]
 impl CloudWatchClient for NoopClient {
-    async fn put_logs(&self, _: LogDestination, _: Vec<LogEvent>) -> Result<(), PutLogsError> {
-        Ok(())
+    fn put_logs<'a>(&'a self, _: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        Box::pin(async { Ok(()) })
     }
 }
 
@@ -37,3 +155,50 @@ impl NoopClient {
         Self {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingClient {
+        batches: Mutex<Vec<BatchMeta>>,
+    }
+
+    impl CloudWatchClient for RecordingClient {
+        fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+            self.batches.lock().unwrap().push(batch.meta);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn forwards_through_smart_pointers() {
+        let recording = Arc::new(RecordingClient::default());
+        let client: Box<dyn CloudWatchClient> = Box::new(recording.clone());
+        let events = ["a", "bcd"].map(|message| LogEvent::new(message, Utc::now()));
+        let destination = LogDestination::default();
+        let batch = LogBatch::new(&destination, &events, 7);
+
+        client.put_logs(batch).await.unwrap();
+        client.put_logs(batch.with_attempt(2)).await.unwrap();
+
+        assert_eq!(
+            *recording.batches.lock().unwrap(),
+            [
+                BatchMeta {
+                    attempt: 1,
+                    batch_id: 7,
+                    byte_size: 4 + 2 * EVENT_OVERHEAD,
+                },
+                BatchMeta {
+                    attempt: 2,
+                    batch_id: 7,
+                    byte_size: 4 + 2 * EVENT_OVERHEAD,
+                },
+            ]
+        );
+    }
+}
@@ -1,4 +1,3 @@
-use async_trait::async_trait;
 use aws_sdk_cloudwatchlogs::{
     Client as SdkClient,
     error::{BuildError, SdkError},
@@ -7,57 +6,96 @@ use aws_sdk_cloudwatchlogs::{
 };
 
 use crate::{
-    client::{CloudWatchClient, LogDestination, PutLogsError},
+    client::{BoxFuture, CloudWatchClient, LogBatch, PutLogsError},
     dispatch::LogEvent,
+    export::LogDestination,
 };
 
-#[async_trait]
 impl CloudWatchClient for SdkClient {
-    async fn put_logs(
-        &self,
-        dest: LogDestination,
-        logs: Vec<LogEvent>,
-    ) -> Result<(), PutLogsError> {
-        let log_events = logs
-            .into_iter()
-            .map(TryFrom::try_from)
-            .collect::<Result<Vec<_>, BuildError>>()
-            .map_err(|err| PutLogsError::Other(err.into()))?;
+    fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        Box::pin(async move {
+            let log_events = batch
+                .events
+                .iter()
+                .map(TryFrom::try_from)
+                .collect::<Result<Vec<_>, BuildError>>()
+                .map_err(|err| PutLogsError::Other(err.into()))?;
 
-        match self
-            .put_log_events()
-            .set_log_events(Some(log_events))
-            .log_group_name(dest.log_group_name)
-            .log_stream_name(dest.log_stream_name)
-            .send()
-            .await
-        {
-            Ok(output) => {
-                if let Some(rejected_info) = output.rejected_log_events_info() {
-                    eprintln!("[tracing-cloudwatch] Put logs rejected: {rejected_info:?}");
+            match self
+                .put_log_events()
+                .set_log_events(Some(log_events))
+                .log_group_name(&batch.destination.log_group_name)
+                .log_stream_name(&batch.destination.log_stream_name)
+                .send()
+                .await
+            {
+                Ok(output) => {
+                    if let Some(rejected_info) = output.rejected_log_events_info() {
+                        eprintln!("[tracing-cloudwatch] Put logs rejected: {rejected_info:?}");
+                    }
+                    Ok(())
                 }
-                Ok(())
+                Err(SdkError::ServiceError(service_err)) => match service_err.into_err() {
+                    PutLogEventsError::ResourceNotFoundException(err) => {
+                        Err(PutLogsError::LogDestinationNotFound {
+                            message: err.message().unwrap_or_default().to_string(),
+                        })
+                    }
+                    PutLogEventsError::InvalidParameterException(err) => {
+                        Err(PutLogsError::InvalidRequest {
+                            message: err.message().unwrap_or_default().to_string(),
+                        })
+                    }
+                    err => Err(anyhow::Error::from(err).into()),
+                },
+                Err(err) => Err(anyhow::Error::from(err).into()),
             }
-            Err(SdkError::ServiceError(service_err)) => match service_err.into_err() {
-                PutLogEventsError::ResourceNotFoundException(err) => {
-                    Err(PutLogsError::LogDestinationNotFound {
-                        message: err.message().unwrap_or_default().to_string(),
-                    })
-                }
-                err => Err(anyhow::Error::from(err).into()),
-            },
-            Err(err) => Err(anyhow::Error::from(err).into()),
-        }
+        })
+    }
+
+    fn ensure_destination<'a>(
+        &'a self,
+        destination: &'a LogDestination,
+    ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        Box::pin(async move {
+            let created = self
+                .create_log_group()
+                .log_group_name(&destination.log_group_name)
+                .send()
+                .await;
+            if let Err(err) = created
+                && !err
+                    .as_service_error()
+                    .is_some_and(|err| err.is_resource_already_exists_exception())
+            {
+                return Err(anyhow::Error::from(err).into());
+            }
+
+            let created = self
+                .create_log_stream()
+                .log_group_name(&destination.log_group_name)
+                .log_stream_name(&destination.log_stream_name)
+                .send()
+                .await;
+            if let Err(err) = created
+                && !err
+                    .as_service_error()
+                    .is_some_and(|err| err.is_resource_already_exists_exception())
+            {
+                return Err(anyhow::Error::from(err).into());
+            }
+            Ok(())
+        })
     }
 }
 
-impl TryFrom<LogEvent> for InputLogEvent {
+impl TryFrom<&LogEvent> for InputLogEvent {
     type Error = BuildError;
 
-    fn try_from(value: LogEvent) -> Result<Self, Self::Error> {
+    fn try_from(value: &LogEvent) -> Result<Self, Self::Error> {
         InputLogEvent::builder()
             .timestamp(value.timestamp.timestamp_millis())
-            .message(value.message)
+            .message(&value.message)
             .build()
     }
 }
@@ -0,0 +1,366 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    client::{BoxFuture, CloudWatchClient, LogBatch, PutLogsError},
+    dispatch::LogEvent,
+    export::LogDestination,
+};
+
+/// Where [`FallbackClient`] writes logs while the primary client is unavailable.
+pub enum LocalSink {
+    Stdout,
+    Stderr,
+    File(RotatingFile),
+}
+
+impl LocalSink {
+    fn write_logs(&mut self, logs: &[LogEvent]) -> std::io::Result<()> {
+        match self {
+            LocalSink::Stdout => write_logs(&mut std::io::stdout().lock(), logs),
+            LocalSink::Stderr => write_logs(&mut std::io::stderr().lock(), logs),
+            LocalSink::File(file) => file.write_logs(logs),
+        }
+    }
+}
+
+/// Write each message on its own line, as it would appear in CloudWatch.
+fn write_logs(writer: &mut impl Write, logs: &[LogEvent]) -> std::io::Result<()> {
+    for log in logs {
+        writer.write_all(log.message.as_bytes())?;
+        if !log.message.ends_with('\n') {
+            writer.write_all(b"\n")?;
+        }
+    }
+    writer.flush()
+}
+
+/// A local file rotated when it grows beyond `max_bytes`.
+///
+/// Rotated files are renamed to `<path>.1`, `<path>.2`, ... and at most `max_files`
+/// of them are kept.
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: Option<(File, u64)>,
+}
+
+impl RotatingFile {
+    pub fn new(path: impl Into<PathBuf>, max_bytes: u64, max_files: usize) -> Self {
+        Self {
+            path: path.into(),
+            max_bytes,
+            max_files,
+            file: None,
+        }
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file = None;
+        if self.max_files == 0 {
+            return std::fs::remove_file(&self.path);
+        }
+        for index in (1..self.max_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                std::fs::rename(from, self.rotated_path(index + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, self.rotated_path(1))
+    }
+
+    fn open(&mut self) -> std::io::Result<&mut (File, u64)> {
+        if self.file.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            let size = file.metadata()?.len();
+            self.file = Some((file, size));
+        }
+        Ok(self.file.as_mut().expect("file was just opened"))
+    }
+
+    fn write_logs(&mut self, logs: &[LogEvent]) -> std::io::Result<()> {
+        let mut buf = Vec::new();
+        write_logs(&mut buf, logs)?;
+
+        let size = self.open()?.1;
+        if size > 0 && size + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        let (file, size) = self.open()?;
+        file.write_all(&buf)?;
+        *size += buf.len() as u64;
+        Ok(())
+    }
+}
+
+/// A [`CloudWatchClient`] writing logs to a [`LocalSink`] while the primary client is unavailable.
+///
+/// When the primary client fails, the batch is written to the sink instead and following batches
+/// go to the sink as well. After `retry_primary_after` the primary client is tried again, and logs
+/// are sent to CloudWatch again once it succeeds.
+///
+/// Place retries inside the primary client, so that only lasting failures switch to the sink.
+/// A [`PutLogsError::InvalidRequest`] is returned as is without switching, since the batch
+/// itself is at fault rather than the primary client.
+///
+/// The sink is written on a blocking thread with [`tokio::task::spawn_blocking`].
+pub struct FallbackClient<C> {
+    primary: Option<C>,
+    sink: Arc<Mutex<LocalSink>>,
+    retry_primary_after: Duration,
+    /// When the primary client last failed, if it is currently unavailable.
+    failed_at: Mutex<Option<Instant>>,
+}
+
+impl<C> FallbackClient<C> {
+    /// Create a client using `primary` if available.
+    ///
+    /// Pass `None` when the primary client cannot be created, e.g. credentials
+    /// are not found at startup, so logs are still written locally.
+    pub fn new(primary: Option<C>, sink: LocalSink) -> Self {
+        Self {
+            primary,
+            sink: Arc::new(Mutex::new(sink)),
+            retry_primary_after: Duration::from_secs(30),
+            failed_at: Mutex::new(None),
+        }
+    }
+
+    /// Set how long to keep writing to the sink before trying the primary client again.
+    /// Default 30 seconds.
+    pub fn with_retry_primary_after(self, retry_primary_after: Duration) -> Self {
+        Self {
+            retry_primary_after,
+            ..self
+        }
+    }
+
+    async fn write_to_sink(&self, logs: &[LogEvent]) -> Result<(), PutLogsError> {
+        let sink = self.sink.clone();
+        let logs = logs.to_vec();
+        tokio::task::spawn_blocking(move || sink.lock().unwrap().write_logs(&logs))
+            .await
+            .map_err(anyhow::Error::from)?
+            .map_err(|err| anyhow::Error::from(err).into())
+    }
+
+    fn should_try_primary(&self) -> bool {
+        self.failed_at
+            .lock()
+            .unwrap()
+            .is_none_or(|failed_at| failed_at.elapsed() >= self.retry_primary_after)
+    }
+}
+
+impl<C: CloudWatchClient> CloudWatchClient for FallbackClient<C> {
+    fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        Box::pin(async move {
+            let Some(primary) = self.primary.as_ref().filter(|_| self.should_try_primary()) else {
+                return self.write_to_sink(batch.events).await;
+            };
+
+            match primary.put_logs(batch).await {
+                Ok(()) => {
+                    if self.failed_at.lock().unwrap().take().is_some() {
+                        eprintln!(
+                            "[tracing-cloudwatch] Primary client recovered, sending logs to CloudWatch again"
+                        );
+                    }
+                    Ok(())
+                }
+                Err(err @ PutLogsError::InvalidRequest { .. }) => Err(err),
+                Err(err) => {
+                    if self
+                        .failed_at
+                        .lock()
+                        .unwrap()
+                        .replace(Instant::now())
+                        .is_none()
+                    {
+                        eprintln!(
+                            "[tracing-cloudwatch] Primary client failed, writing logs locally. Error: {err:?}"
+                        );
+                    }
+                    self.write_to_sink(batch.events).await
+                }
+            }
+        })
+    }
+
+    fn ensure_destination<'a>(
+        &'a self,
+        destination: &'a LogDestination,
+    ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        match &self.primary {
+            Some(primary) => primary.ensure_destination(destination),
+            None => Box::pin(async { Ok(()) }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::LogDestination;
+    use chrono::Utc;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct FlakyClient {
+        failing: AtomicBool,
+        calls: AtomicUsize,
+    }
+
+    impl CloudWatchClient for FlakyClient {
+        fn put_logs<'a>(&'a self, _: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            let failing = self.failing.load(Ordering::Relaxed);
+            Box::pin(async move {
+                if failing {
+                    Err(anyhow::anyhow!("unavailable").into())
+                } else {
+                    Ok(())
+                }
+            })
+        }
+    }
+
+    async fn put(client: &impl CloudWatchClient, messages: &[&str]) -> Result<(), PutLogsError> {
+        let events = logs(messages);
+        client
+            .put_logs(LogBatch::new(&LogDestination::default(), &events, 0))
+            .await
+    }
+
+    fn logs(messages: &[&str]) -> Vec<LogEvent> {
+        messages
+            .iter()
+            .map(|&message| LogEvent::new(message, Utc::now()))
+            .collect()
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn falls_back_and_recovers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fallback.log");
+        let primary = FlakyClient::default();
+        primary.failing.store(true, Ordering::Relaxed);
+
+        let client = FallbackClient::new(
+            Some(primary),
+            LocalSink::File(RotatingFile::new(&path, 1024, 1)),
+        )
+        .with_retry_primary_after(Duration::ZERO);
+
+        put(&client, &["a\n", "b"]).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\nb\n");
+
+        let primary = client.primary.as_ref().unwrap();
+        primary.failing.store(false, Ordering::Relaxed);
+        put(&client, &["c"]).await.unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\nb\n");
+        assert_eq!(primary.calls.load(Ordering::Relaxed), 2);
+        assert!(client.failed_at.lock().unwrap().is_none());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn skips_primary_until_retry_interval() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fallback.log");
+        let primary = FlakyClient::default();
+        primary.failing.store(true, Ordering::Relaxed);
+
+        let client = FallbackClient::new(
+            Some(primary),
+            LocalSink::File(RotatingFile::new(&path, 1024, 1)),
+        )
+        .with_retry_primary_after(Duration::from_secs(60));
+
+        for message in ["a", "b", "c"] {
+            put(&client, &[message]).await.unwrap();
+        }
+
+        assert_eq!(
+            client
+                .primary
+                .as_ref()
+                .unwrap()
+                .calls
+                .load(Ordering::Relaxed),
+            1
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\nb\nc\n");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn keeps_primary_on_invalid_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fallback.log");
+        let primary = crate::client::middleware::tests::ScriptedClient::new(vec![Err(
+            PutLogsError::InvalidRequest {
+                message: "bad batch".to_string(),
+            },
+        )]);
+        let client = FallbackClient::new(
+            Some(primary),
+            LocalSink::File(RotatingFile::new(&path, 1024, 1)),
+        );
+
+        let err = put(&client, &["bad"]).await.unwrap_err();
+        assert!(matches!(err, PutLogsError::InvalidRequest { .. }), "{err}");
+        put(&client, &["good"]).await.unwrap();
+
+        assert_eq!(client.primary.as_ref().unwrap().calls(), 2);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn writes_locally_without_primary() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fallback.log");
+        let client = FallbackClient::<FlakyClient>::new(
+            None,
+            LocalSink::File(RotatingFile::new(&path, 1024, 1)),
+        );
+
+        put(&client, &["local"]).await.unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "local\n");
+    }
+
+    #[test]
+    fn rotates_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fallback.log");
+        let mut file = RotatingFile::new(&path, 8, 2);
+
+        for message in ["1111", "2222", "3333", "4444"] {
+            file.write_logs(&logs(&[message])).unwrap();
+        }
+
+        let read = |suffix: &str| {
+            std::fs::read_to_string(dir.path().join(format!("fallback.log{suffix}"))).unwrap()
+        };
+        assert_eq!(read(""), "4444\n");
+        assert_eq!(read(".1"), "3333\n");
+        assert_eq!(read(".2"), "2222\n");
+        assert!(!dir.path().join("fallback.log.3").exists());
+    }
+}
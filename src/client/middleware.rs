@@ -0,0 +1,97 @@
+//! Composable wrappers adding behavior around a [`CloudWatchClient`].
+//!
+//! See [`CloudWatchClientExt::with`] for how layers are stacked.
+
+mod circuit_breaker;
+mod metrics;
+mod rate_limit;
+mod retry;
+mod timeout;
+
+pub(crate) use circuit_breaker::{Admission, CircuitState, Transition};
+pub use circuit_breaker::{CircuitBreaker, CircuitBreakerClient};
+pub use metrics::{Metrics, MetricsClient, MetricsSnapshot};
+pub use rate_limit::{RateLimit, RateLimitClient};
+pub use retry::{Retry, RetryClient};
+pub use timeout::{Timeout, TimeoutClient};
+
+use crate::client::CloudWatchClient;
+
+/// Wrap a client into another client, like `tower::Layer`.
+pub trait ClientLayer<C> {
+    type Client: CloudWatchClient;
+
+    fn layer(self, inner: C) -> Self::Client;
+}
+
+/// Extension trait to stack [`ClientLayer`]s on a client.
+pub trait CloudWatchClientExt: CloudWatchClient + Sized {
+    /// Wrap this client with `layer`.
+    ///
+    /// Layers apply in order, so the last one added is the outermost.
+    fn with<L>(self, layer: L) -> L::Client
+    where
+        L: ClientLayer<Self>,
+    {
+        layer.layer(self)
+    }
+}
+
+impl<C: CloudWatchClient> CloudWatchClientExt for C {}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use chrono::Utc;
+
+    use super::*;
+    use crate::{
+        client::{BoxFuture, LogBatch, PutLogsError},
+        dispatch::LogEvent,
+        export::LogDestination,
+    };
+
+    /// Client returning the scripted results in order, then succeeding.
+    #[derive(Default)]
+    pub(crate) struct ScriptedClient {
+        results: Mutex<Vec<Result<(), PutLogsError>>>,
+        pub(crate) calls: AtomicUsize,
+    }
+
+    impl ScriptedClient {
+        pub(crate) fn new(mut results: Vec<Result<(), PutLogsError>>) -> Self {
+            results.reverse();
+            Self {
+                results: Mutex::new(results),
+                calls: AtomicUsize::new(0),
+            }
+        }
+
+        pub(crate) fn calls(&self) -> usize {
+            self.calls.load(Ordering::Relaxed)
+        }
+    }
+
+    impl CloudWatchClient for ScriptedClient {
+        fn put_logs<'a>(&'a self, _: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            let result = self.results.lock().unwrap().pop().unwrap_or(Ok(()));
+            Box::pin(async { result })
+        }
+    }
+
+    pub(crate) fn unavailable() -> Result<(), PutLogsError> {
+        Err(anyhow::anyhow!("unavailable").into())
+    }
+
+    pub(crate) async fn put(client: &impl CloudWatchClient) -> Result<(), PutLogsError> {
+        let events = [LogEvent::new("message", Utc::now())];
+        client
+            .put_logs(LogBatch::new(&LogDestination::default(), &events, 0))
+            .await
+    }
+}
@@ -0,0 +1,169 @@
+use std::{sync::Mutex, time::Duration};
+
+use tokio::time::Instant;
+
+use super::ClientLayer;
+use crate::{
+    client::{BoxFuture, CloudWatchClient, LogBatch, PutLogsError},
+    export::LogDestination,
+};
+
+/// Stop calling the inner client after consecutive failures.
+///
+/// After `failure_threshold` consecutive failures the circuit opens and batches fail immediately
+/// without calling the inner client. Once `reset_after` has elapsed, the next batch is sent as a
+/// probe: the circuit closes if it succeeds and opens again otherwise.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    reset_after: Duration,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, reset_after: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            reset_after,
+        }
+    }
+}
+
+impl<C: CloudWatchClient> ClientLayer<C> for CircuitBreaker {
+    type Client = CircuitBreakerClient<C>;
+
+    fn layer(self, inner: C) -> Self::Client {
+        CircuitBreakerClient {
+            inner,
+            state: Mutex::new(CircuitState::default()),
+            config: self,
+        }
+    }
+}
+
+/// Consecutive failures and when the circuit opened, shared by [`CircuitBreaker`] and the
+/// exporter's [`ExportConfig::with_circuit_breaker`](crate::ExportConfig::with_circuit_breaker).
+#[derive(Debug, Default)]
+pub(crate) struct CircuitState {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+/// What to do with the next batch.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Admission {
+    Send,
+    /// Send the batch as a probe after the cooldown.
+    Probe,
+    Reject,
+}
+
+/// State change of a [`CircuitState`].
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Transition {
+    Opened { consecutive_failures: u32 },
+    Closed,
+}
+
+impl CircuitState {
+    pub(crate) fn admit(&mut self, reset_after: Duration) -> Admission {
+        match self.opened_at {
+            None => Admission::Send,
+            Some(opened_at) if opened_at.elapsed() < reset_after => Admission::Reject,
+            Some(_) => {
+                // Keep rejecting other batches until the probe completes.
+                self.opened_at = Some(Instant::now());
+                Admission::Probe
+            }
+        }
+    }
+
+    /// Record the result of a batch, returning the state change if any. A `failure_threshold` of
+    /// 0 never opens the circuit.
+    pub(crate) fn record(&mut self, success: bool, failure_threshold: u32) -> Option<Transition> {
+        if success {
+            let was_open = self.opened_at.is_some();
+            *self = Self::default();
+            return was_open.then_some(Transition::Closed);
+        }
+
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if failure_threshold == 0 || self.consecutive_failures < failure_threshold {
+            return None;
+        }
+        self.opened_at = Some(Instant::now());
+        Some(Transition::Opened {
+            consecutive_failures: self.consecutive_failures,
+        })
+    }
+}
+
+/// Client created by [`CircuitBreaker`].
+pub struct CircuitBreakerClient<C> {
+    inner: C,
+    state: Mutex<CircuitState>,
+    config: CircuitBreaker,
+}
+
+impl<C> CircuitBreakerClient<C> {
+    fn is_open(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.admit(self.config.reset_after) == Admission::Reject
+    }
+
+    fn record(&self, success: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.record(success, self.config.failure_threshold);
+    }
+}
+
+impl<C: CloudWatchClient> CloudWatchClient for CircuitBreakerClient<C> {
+    fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        Box::pin(async move {
+            if self.is_open() {
+                return Err(anyhow::anyhow!("circuit breaker is open").into());
+            }
+            let result = self.inner.put_logs(batch).await;
+            self.record(result.is_ok());
+            result
+        })
+    }
+
+    fn ensure_destination<'a>(
+        &'a self,
+        destination: &'a LogDestination,
+    ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        self.inner.ensure_destination(destination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::middleware::{
+        CloudWatchClientExt,
+        tests::{ScriptedClient, put, unavailable},
+    };
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn opens_and_recovers() {
+        let client = ScriptedClient::new((0..3).map(|_| unavailable()).collect())
+            .with(CircuitBreaker::new(2, Duration::from_secs(10)));
+
+        assert!(put(&client).await.is_err());
+        assert!(put(&client).await.is_err());
+        // Open: the inner client is not called.
+        assert!(put(&client).await.is_err());
+        assert_eq!(client.inner.calls(), 2);
+
+        // Failed probe opens the circuit again.
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert!(put(&client).await.is_err());
+        assert!(put(&client).await.is_err());
+        assert_eq!(client.inner.calls(), 3);
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        put(&client).await.unwrap();
+        put(&client).await.unwrap();
+        assert_eq!(client.inner.calls(), 5);
+    }
+}
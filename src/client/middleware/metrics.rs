@@ -0,0 +1,130 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use super::ClientLayer;
+use crate::{
+    client::{BoxFuture, CloudWatchClient, LogBatch, PutLogsError},
+    export::LogDestination,
+};
+
+/// Count sent and failed batches and events, and the time spent sending them.
+///
+/// Keep a clone of the layer to read the counters with [`Metrics::snapshot`].
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    counters: Arc<Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    batches_sent: AtomicU64,
+    batches_failed: AtomicU64,
+    events_sent: AtomicU64,
+    events_failed: AtomicU64,
+    latency_nanos: AtomicU64,
+}
+
+/// Counters of a [`Metrics`] layer at some point in time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub batches_sent: u64,
+    pub batches_failed: u64,
+    pub events_sent: u64,
+    pub events_failed: u64,
+    /// Total time spent in the inner client.
+    pub latency: Duration,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let counters = &self.counters;
+        MetricsSnapshot {
+            batches_sent: counters.batches_sent.load(Ordering::Relaxed),
+            batches_failed: counters.batches_failed.load(Ordering::Relaxed),
+            events_sent: counters.events_sent.load(Ordering::Relaxed),
+            events_failed: counters.events_failed.load(Ordering::Relaxed),
+            latency: Duration::from_nanos(counters.latency_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl<C: CloudWatchClient> ClientLayer<C> for Metrics {
+    type Client = MetricsClient<C>;
+
+    fn layer(self, inner: C) -> Self::Client {
+        MetricsClient {
+            inner,
+            metrics: self,
+        }
+    }
+}
+
+/// Client created by [`Metrics`].
+pub struct MetricsClient<C> {
+    inner: C,
+    metrics: Metrics,
+}
+
+impl<C: CloudWatchClient> CloudWatchClient for MetricsClient<C> {
+    fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        Box::pin(async move {
+            let events = batch.events.len() as u64;
+            let start = Instant::now();
+            let result = self.inner.put_logs(batch).await;
+
+            let counters = &self.metrics.counters;
+            let latency = u64::try_from(start.elapsed().as_nanos()).unwrap_or(u64::MAX);
+            counters.latency_nanos.fetch_add(latency, Ordering::Relaxed);
+            let (batches, events_counter) = if result.is_ok() {
+                (&counters.batches_sent, &counters.events_sent)
+            } else {
+                (&counters.batches_failed, &counters.events_failed)
+            };
+            batches.fetch_add(1, Ordering::Relaxed);
+            events_counter.fetch_add(events, Ordering::Relaxed);
+
+            result
+        })
+    }
+
+    fn ensure_destination<'a>(
+        &'a self,
+        destination: &'a LogDestination,
+    ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        self.inner.ensure_destination(destination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::middleware::{
+        CloudWatchClientExt,
+        tests::{ScriptedClient, put, unavailable},
+    };
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn counts_batches_and_events() {
+        let metrics = Metrics::new();
+        let client = ScriptedClient::new(vec![unavailable()]).with(metrics.clone());
+
+        assert!(put(&client).await.is_err());
+        put(&client).await.unwrap();
+        put(&client).await.unwrap();
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.batches_sent, 2);
+        assert_eq!(snapshot.batches_failed, 1);
+        assert_eq!(snapshot.events_sent, 2);
+        assert_eq!(snapshot.events_failed, 1);
+    }
+}
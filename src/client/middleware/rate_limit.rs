@@ -0,0 +1,122 @@
+use std::{sync::Mutex, time::Duration};
+
+use tokio::time::Instant;
+
+use super::ClientLayer;
+use crate::{
+    client::{BoxFuture, CloudWatchClient, LogBatch, PutLogsError},
+    export::LogDestination,
+};
+
+/// Limit the number of batches sent per period, waiting for the next slot when exceeded.
+///
+/// Up to `batches` batches can be sent in a burst, then slots are refilled evenly over `per`.
+/// CloudWatch Logs throttles `PutLogEvents` per account and region, so this helps several
+/// processes to share the quota.
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    batches: u32,
+    per: Duration,
+}
+
+impl RateLimit {
+    pub fn new(batches: u32, per: Duration) -> Self {
+        Self {
+            batches: batches.max(1),
+            per,
+        }
+    }
+}
+
+impl<C: CloudWatchClient> ClientLayer<C> for RateLimit {
+    type Client = RateLimitClient<C>;
+
+    fn layer(self, inner: C) -> Self::Client {
+        RateLimitClient {
+            inner,
+            bucket: Mutex::new(Bucket {
+                tokens: f64::from(self.batches),
+                refilled_at: Instant::now(),
+            }),
+            config: self,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Client created by [`RateLimit`].
+pub struct RateLimitClient<C> {
+    inner: C,
+    bucket: Mutex<Bucket>,
+    config: RateLimit,
+}
+
+impl<C> RateLimitClient<C> {
+    /// Take a token, or return how long to wait until one is available.
+    fn try_acquire(&self) -> Result<(), Duration> {
+        let capacity = f64::from(self.config.batches);
+        let per_token = self.config.per.as_secs_f64() / capacity;
+        let mut bucket = self.bucket.lock().unwrap();
+
+        let now = Instant::now();
+        if per_token > 0.0 {
+            let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed / per_token).min(capacity);
+        } else {
+            bucket.tokens = capacity;
+        }
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) * per_token))
+        }
+    }
+}
+
+impl<C: CloudWatchClient> CloudWatchClient for RateLimitClient<C> {
+    fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        Box::pin(async move {
+            while let Err(wait) = self.try_acquire() {
+                tokio::time::sleep(wait).await;
+            }
+            self.inner.put_logs(batch).await
+        })
+    }
+
+    fn ensure_destination<'a>(
+        &'a self,
+        destination: &'a LogDestination,
+    ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        self.inner.ensure_destination(destination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::middleware::{
+        CloudWatchClientExt,
+        tests::{ScriptedClient, put},
+    };
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn waits_for_next_slot() {
+        let client = ScriptedClient::default().with(RateLimit::new(2, Duration::from_secs(1)));
+        let start = Instant::now();
+
+        put(&client).await.unwrap();
+        put(&client).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        put(&client).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(500));
+        assert_eq!(client.inner.calls(), 3);
+    }
+}
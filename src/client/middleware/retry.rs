@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use super::ClientLayer;
+use crate::{
+    client::{BoxFuture, CloudWatchClient, LogBatch, PutLogsError},
+    export::LogDestination,
+};
+
+/// Retry failed batches with exponential backoff.
+///
+/// [`PutLogsError::LogDestinationNotFound`] and [`PutLogsError::InvalidRequest`] are not
+/// retried, since sending the same batch again cannot succeed.
+#[derive(Debug, Clone)]
+pub struct Retry {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Retry {
+    /// Make at most `max_attempts` attempts per batch, including the first one.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+
+    /// Set the delay before the first retry, doubled for each following retry. Default 100ms.
+    pub fn with_initial_backoff(self, initial_backoff: Duration) -> Self {
+        Self {
+            initial_backoff,
+            ..self
+        }
+    }
+
+    /// Set the maximum delay between retries. Default 5 seconds.
+    pub fn with_max_backoff(self, max_backoff: Duration) -> Self {
+        Self {
+            max_backoff,
+            ..self
+        }
+    }
+}
+
+impl<C: CloudWatchClient> ClientLayer<C> for Retry {
+    type Client = RetryClient<C>;
+
+    fn layer(self, inner: C) -> Self::Client {
+        RetryClient {
+            inner,
+            config: self,
+        }
+    }
+}
+
+/// Client created by [`Retry`].
+pub struct RetryClient<C> {
+    inner: C,
+    config: Retry,
+}
+
+impl<C: CloudWatchClient> CloudWatchClient for RetryClient<C> {
+    fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        Box::pin(async move {
+            let mut backoff = self.config.initial_backoff;
+            let mut attempt = batch;
+            for _ in 1..self.config.max_attempts {
+                match self.inner.put_logs(attempt).await {
+                    Err(PutLogsError::Other(_)) => {
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(self.config.max_backoff);
+                        attempt = attempt.with_attempt(attempt.meta.attempt + 1);
+                    }
+                    result => return result,
+                }
+            }
+            self.inner.put_logs(attempt).await
+        })
+    }
+
+    fn ensure_destination<'a>(
+        &'a self,
+        destination: &'a LogDestination,
+    ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        self.inner.ensure_destination(destination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::middleware::{
+        CloudWatchClientExt,
+        tests::{ScriptedClient, put, unavailable},
+    };
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn retries_until_success() {
+        let client = ScriptedClient::new(vec![unavailable(), unavailable()]).with(Retry::new(3));
+
+        put(&client).await.unwrap();
+
+        assert_eq!(client.inner.calls(), 3);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn gives_up_after_max_attempts() {
+        let client =
+            ScriptedClient::new((0..3).map(|_| unavailable()).collect()).with(Retry::new(2));
+
+        assert!(put(&client).await.is_err());
+        assert_eq!(client.inner.calls(), 2);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn does_not_retry_missing_destination() {
+        let client = ScriptedClient::new(vec![Err(PutLogsError::LogDestinationNotFound {
+            message: "missing".into(),
+        })])
+        .with(Retry::new(3));
+
+        assert!(put(&client).await.is_err());
+        assert_eq!(client.inner.calls(), 1);
+    }
+}
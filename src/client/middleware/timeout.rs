@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use super::ClientLayer;
+use crate::{
+    client::{BoxFuture, CloudWatchClient, LogBatch, PutLogsError},
+    export::LogDestination,
+};
+
+/// Fail batches which are not sent within a duration.
+#[derive(Debug, Clone)]
+pub struct Timeout {
+    timeout: Duration,
+}
+
+impl Timeout {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+impl<C: CloudWatchClient> ClientLayer<C> for Timeout {
+    type Client = TimeoutClient<C>;
+
+    fn layer(self, inner: C) -> Self::Client {
+        TimeoutClient {
+            inner,
+            timeout: self.timeout,
+        }
+    }
+}
+
+/// Client created by [`Timeout`].
+pub struct TimeoutClient<C> {
+    inner: C,
+    timeout: Duration,
+}
+
+impl<C: CloudWatchClient> CloudWatchClient for TimeoutClient<C> {
+    fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        Box::pin(async move {
+            tokio::time::timeout(self.timeout, self.inner.put_logs(batch))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(anyhow::anyhow!("put_logs timed out after {:?}", self.timeout).into())
+                })
+        })
+    }
+
+    fn ensure_destination<'a>(
+        &'a self,
+        destination: &'a LogDestination,
+    ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        self.inner.ensure_destination(destination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::middleware::{CloudWatchClientExt, tests::put};
+
+    struct SlowClient;
+
+    impl CloudWatchClient for SlowClient {
+        fn put_logs<'a>(&'a self, _: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+            Box::pin(async {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok(())
+            })
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn times_out() {
+        let client = SlowClient.with(Timeout::new(Duration::from_secs(1)));
+
+        let err = put(&client).await.unwrap_err();
+
+        assert_eq!(err.to_string(), "put_logs timed out after 1s");
+    }
+}
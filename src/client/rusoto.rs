@@ -1,53 +1,83 @@
-use async_trait::async_trait;
 use rusoto_core::RusotoError;
 use rusoto_logs::{
-    CloudWatchLogs, CloudWatchLogsClient as SdkClient, InputLogEvent, PutLogEventsError,
+    CloudWatchLogs, CloudWatchLogsClient as SdkClient, CreateLogGroupError, CreateLogGroupRequest,
+    CreateLogStreamError, CreateLogStreamRequest, InputLogEvent, PutLogEventsError,
     PutLogEventsRequest,
 };
 
 use crate::{
-    client::{CloudWatchClient, LogDestination, PutLogsError},
+    client::{BoxFuture, CloudWatchClient, LogBatch, PutLogsError},
     dispatch::LogEvent,
+    export::LogDestination,
 };
 
-#[async_trait]
 impl CloudWatchClient for SdkClient {
-    async fn put_logs(
-        &self,
-        dest: LogDestination,
-        logs: Vec<LogEvent>,
-    ) -> Result<(), PutLogsError> {
-        let log_events = logs.into_iter().map(From::from).collect();
+    fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        Box::pin(async move {
+            let log_events = batch.events.iter().map(From::from).collect();
 
-        let input = PutLogEventsRequest {
-            log_events,
-            log_group_name: dest.log_group_name,
-            log_stream_name: dest.log_stream_name,
-            sequence_token: None,
-        };
+            let input = PutLogEventsRequest {
+                log_events,
+                log_group_name: batch.destination.log_group_name.clone(),
+                log_stream_name: batch.destination.log_stream_name.clone(),
+                sequence_token: None,
+            };
 
-        // TODO: retry
-        // Is the next sequence token no longer used?
-        // https://docs.aws.amazon.com/AmazonCloudWatchLogs/latest/APIReference/API_PutLogEvents.html
-        match self.put_log_events(input).await {
-            Ok(response) => {
-                if let Some(rejected_info) = response.rejected_log_events_info {
-                    eprintln!("[tracing-cloudwatch] Put logs rejected: {rejected_info:?}")
+            // TODO: retry
+            // Is the next sequence token no longer used?
+            // https://docs.aws.amazon.com/AmazonCloudWatchLogs/latest/APIReference/API_PutLogEvents.html
+            match self.put_log_events(input).await {
+                Ok(response) => {
+                    if let Some(rejected_info) = response.rejected_log_events_info {
+                        eprintln!("[tracing-cloudwatch] Put logs rejected: {rejected_info:?}")
+                    }
+                    Ok(())
                 }
+                Err(RusotoError::Service(PutLogEventsError::ResourceNotFound(message))) => {
+                    Err(PutLogsError::LogDestinationNotFound { message })
+                }
+                Err(RusotoError::Service(PutLogEventsError::InvalidParameter(message))) => {
+                    Err(PutLogsError::InvalidRequest { message })
+                }
+                Err(err) => Err(anyhow::Error::from(err).into()),
+            }
+        })
+    }
+
+    fn ensure_destination<'a>(
+        &'a self,
+        destination: &'a LogDestination,
+    ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        Box::pin(async move {
+            let input = CreateLogGroupRequest {
+                log_group_name: destination.log_group_name.clone(),
+                ..Default::default()
+            };
+            match self.create_log_group(input).await {
                 Ok(())
+                | Err(RusotoError::Service(CreateLogGroupError::ResourceAlreadyExists(_))) => {}
+                Err(err) => return Err(anyhow::Error::from(err).into()),
             }
-            Err(RusotoError::Service(PutLogEventsError::ResourceNotFound(message))) => {
-                Err(PutLogsError::LogDestinationNotFound { message })
+
+            let input = CreateLogStreamRequest {
+                log_group_name: destination.log_group_name.clone(),
+                log_stream_name: destination.log_stream_name.clone(),
+            };
+            match self.create_log_stream(input).await {
+                Ok(())
+                | Err(RusotoError::Service(CreateLogStreamError::ResourceAlreadyExists(_))) => {
+                    Ok(())
+                }
+                Err(err) => Err(anyhow::Error::from(err).into()),
             }
-            Err(err) => Err(anyhow::Error::from(err).into()),
-        }
+        })
     }
 }
 
-impl From<LogEvent> for InputLogEvent {
-    fn from(value: LogEvent) -> Self {
+impl From<&LogEvent> for InputLogEvent {
+    fn from(value: &LogEvent) -> Self {
         Self {
-            message: value.message,
+            message: value.message.clone(),
             timestamp: value.timestamp.timestamp_millis(),
         }
     }
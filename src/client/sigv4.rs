@@ -0,0 +1,394 @@
+pub(crate) mod credentials;
+mod signing;
+
+use chrono::Utc;
+use hyper::{Body, Request, Uri};
+use serde_json::{Value, json};
+
+use crate::{
+    client::{BoxFuture, CloudWatchClient, LogBatch, PutLogsError},
+    export::LogDestination,
+    metadata::{HttpClient, http_client},
+};
+
+pub use credentials::{Credentials, CredentialsError, CredentialsProvider};
+use signing::{SignableRequest, SigningScope, amz_date, authorization};
+
+const SERVICE: &str = "logs";
+const PUT_LOG_EVENTS_TARGET: &str = "Logs_20140328.PutLogEvents";
+const CREATE_LOG_GROUP_TARGET: &str = "Logs_20140328.CreateLogGroup";
+const CREATE_LOG_STREAM_TARGET: &str = "Logs_20140328.CreateLogStream";
+const CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+
+/// A lightweight [`CloudWatchClient`] calling the CloudWatch Logs JSON API directly
+/// with AWS Signature Version 4, without the AWS SDK or rusoto.
+///
+/// feature `sigv4` required
+///
+/// ```rust,no_run
+/// # async fn run() {
+/// let cw_client = tracing_cloudwatch::SigV4Client::new("ap-northeast-1");
+///
+/// let (cw_layer, cw_guard) = tracing_cloudwatch::layer::<tracing_subscriber::Registry>()
+///     .with_client(
+///         cw_client,
+///         tracing_cloudwatch::ExportConfig::default()
+///             .with_log_group_name("tracing-cloudwatch")
+///             .with_log_stream_name("stream-1"),
+///     );
+/// # }
+/// ```
+pub struct SigV4Client {
+    http: HttpClient,
+    region: String,
+    endpoint: Uri,
+    credentials: CredentialsProvider,
+}
+
+impl SigV4Client {
+    /// Create a client for the region, resolving credentials with the
+    /// [default chain](CredentialsProvider).
+    ///
+    /// # Panics
+    ///
+    /// If `region` is not made of lowercase ASCII letters, digits and hyphens, see
+    /// [`Self::try_new`] for regions read from configurations.
+    pub fn new(region: impl Into<String>) -> Self {
+        Self::try_new(region).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Create a client for the region like [`Self::new`], failing if `region` is not made of
+    /// lowercase ASCII letters, digits and hyphens, e.g. `ap-northeast-1`.
+    pub fn try_new(region: impl Into<String>) -> Result<Self, InvalidRegion> {
+        let region = region.into();
+        let valid = !region.is_empty()
+            && region
+                .bytes()
+                .all(|byte| byte.is_ascii_lowercase() || byte.is_ascii_digit() || byte == b'-');
+        if !valid {
+            return Err(InvalidRegion { region });
+        }
+        let endpoint = format!("https://logs.{region}.amazonaws.com/")
+            .parse()
+            .expect("a valid region makes a valid endpoint");
+        Ok(Self {
+            http: http_client(),
+            region,
+            endpoint,
+            credentials: CredentialsProvider::default_chain(),
+        })
+    }
+
+    /// Set the endpoint, e.g. a VPC endpoint or a local stand-in server.
+    pub fn with_endpoint(self, endpoint: Uri) -> Self {
+        Self { endpoint, ..self }
+    }
+
+    /// Set how credentials are resolved.
+    pub fn with_credentials(self, credentials: CredentialsProvider) -> Self {
+        Self {
+            credentials,
+            ..self
+        }
+    }
+
+    async fn call(&self, target: &str, payload: Value) -> Result<Value, PutLogsError> {
+        let credentials = self
+            .credentials
+            .credentials(&self.http)
+            .await
+            .map_err(anyhow::Error::from)?;
+
+        let body = payload.to_string();
+        let host = match self.endpoint.port() {
+            Some(port) => format!("{}:{port}", self.endpoint.host().unwrap_or_default()),
+            None => self.endpoint.host().unwrap_or_default().to_string(),
+        };
+        let now = Utc::now();
+        let date = amz_date(now);
+
+        let mut headers = vec![
+            ("content-type", CONTENT_TYPE),
+            ("host", host.as_str()),
+            ("x-amz-date", date.as_str()),
+            ("x-amz-target", target),
+        ];
+        if let Some(token) = credentials.session_token.as_deref() {
+            headers.push(("x-amz-security-token", token));
+        }
+
+        let authorization = authorization(
+            &SignableRequest {
+                method: "POST",
+                path: self.endpoint.path(),
+                query: self.endpoint.query().unwrap_or_default(),
+                headers: &headers,
+                payload: body.as_bytes(),
+            },
+            &SigningScope {
+                region: &self.region,
+                service: SERVICE,
+                time: now,
+            },
+            &credentials,
+        );
+
+        let mut request = Request::post(self.endpoint.clone());
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let request = request
+            .header("authorization", authorization)
+            .body(Body::from(body))
+            .map_err(anyhow::Error::from)?;
+
+        let response = self
+            .http
+            .request(request)
+            .await
+            .map_err(anyhow::Error::from)?;
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .map_err(anyhow::Error::from)?;
+        let value: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+
+        if status.is_success() {
+            return Ok(value);
+        }
+
+        // Error types are sent as `__type`, optionally prefixed with a namespace.
+        let error_type = value["__type"]
+            .as_str()
+            .and_then(|ty| ty.rsplit('#').next())
+            .unwrap_or_default();
+        let message = value["message"]
+            .as_str()
+            .or_else(|| value["Message"].as_str())
+            .unwrap_or_default()
+            .to_string();
+
+        match error_type {
+            "ResourceNotFoundException" => Err(PutLogsError::LogDestinationNotFound { message }),
+            "InvalidParameterException" => Err(PutLogsError::InvalidRequest { message }),
+            // Creating a log group or stream which already exists is fine.
+            "ResourceAlreadyExistsException" if target != PUT_LOG_EVENTS_TARGET => Ok(value),
+            _ => Err(anyhow::anyhow!("{status} {error_type}: {message}").into()),
+        }
+    }
+}
+
+impl CloudWatchClient for SigV4Client {
+    fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        Box::pin(async move {
+            let log_events: Vec<Value> = batch
+                .events
+                .iter()
+                .map(|event| {
+                    json!({
+                        "timestamp": event.timestamp.timestamp_millis(),
+                        "message": event.message,
+                    })
+                })
+                .collect();
+
+            let output = self
+                .call(
+                    PUT_LOG_EVENTS_TARGET,
+                    json!({
+                        "logGroupName": batch.destination.log_group_name,
+                        "logStreamName": batch.destination.log_stream_name,
+                        "logEvents": log_events,
+                    }),
+                )
+                .await?;
+
+            if let Some(rejected_info) = output.get("rejectedLogEventsInfo") {
+                eprintln!("[tracing-cloudwatch] Put logs rejected: {rejected_info}");
+            }
+            Ok(())
+        })
+    }
+
+    fn ensure_destination<'a>(
+        &'a self,
+        destination: &'a LogDestination,
+    ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        Box::pin(async move {
+            self.call(
+                CREATE_LOG_GROUP_TARGET,
+                json!({ "logGroupName": destination.log_group_name }),
+            )
+            .await?;
+            self.call(
+                CREATE_LOG_STREAM_TARGET,
+                json!({
+                    "logGroupName": destination.log_group_name,
+                    "logStreamName": destination.log_stream_name,
+                }),
+            )
+            .await?;
+            Ok(())
+        })
+    }
+}
+
+/// Error of [`SigV4Client::try_new`].
+#[derive(Debug, thiserror::Error)]
+#[error("invalid region {region:?}, expected lowercase letters, digits and hyphens")]
+pub struct InvalidRegion {
+    pub region: String,
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{dispatch::LogEvent, export::LogDestination, metadata::tests::StandIn};
+
+    fn client(stand_in: &StandIn) -> SigV4Client {
+        SigV4Client::new("us-east-1")
+            .with_endpoint(format!("{}/", stand_in.url()).parse().unwrap())
+            .with_credentials(CredentialsProvider::static_credentials(Credentials::new(
+                "AKID",
+                "secret",
+                Some("session".to_string()),
+            )))
+    }
+
+    fn dest() -> LogDestination {
+        LogDestination {
+            log_group_name: "group".to_string(),
+            log_stream_name: "stream".to_string(),
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_regions() {
+        assert!(SigV4Client::try_new("ap-northeast-1").is_ok());
+        for region in ["", "us-east-1/", "US-EAST-1", "us east 1"] {
+            let err = SigV4Client::try_new(region).err().unwrap();
+            assert_eq!(err.region, region);
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn puts_signed_log_events() {
+        let stand_in =
+            StandIn::start(vec![(200, r#"{"nextSequenceToken":"1"}"#.to_string())]).await;
+        let timestamp = chrono::DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+
+        let events = [LogEvent::new("hello", timestamp)];
+
+        client(&stand_in)
+            .put_logs(LogBatch::new(&dest(), &events, 0))
+            .await
+            .unwrap();
+
+        let request = &stand_in.requests()[0];
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.header("x-amz-target"), Some(PUT_LOG_EVENTS_TARGET));
+        assert_eq!(request.header("content-type"), Some(CONTENT_TYPE));
+        assert_eq!(request.header("x-amz-security-token"), Some("session"));
+        let authorization = request.header("authorization").unwrap();
+        assert!(
+            authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKID/"),
+            "{authorization}"
+        );
+        assert!(authorization.contains("/us-east-1/logs/aws4_request"));
+        assert!(authorization.contains(
+            "SignedHeaders=content-type;host;x-amz-date;x-amz-security-token;x-amz-target"
+        ));
+        assert_eq!(
+            serde_json::from_str::<Value>(&request.body).unwrap(),
+            json!({
+                "logGroupName": "group",
+                "logStreamName": "stream",
+                "logEvents": [{ "timestamp": 1_700_000_000_000_i64, "message": "hello" }],
+            })
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn maps_resource_not_found() {
+        let stand_in = StandIn::start(vec![(
+            400,
+            r#"{"__type":"com.amazonaws.logs#ResourceNotFoundException","message":"The specified log group does not exist."}"#.to_string(),
+        )])
+        .await;
+
+        let err = client(&stand_in)
+            .put_logs(LogBatch::new(&dest(), &[], 0))
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(err, PutLogsError::LogDestinationNotFound { ref message } if message == "The specified log group does not exist."),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn maps_invalid_parameter() {
+        let stand_in = StandIn::start(vec![(
+            400,
+            r#"{"__type":"InvalidParameterException","message":"Log events must be in chronological order."}"#.to_string(),
+        )])
+        .await;
+
+        let err = client(&stand_in)
+            .put_logs(LogBatch::new(&dest(), &[], 0))
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(err, PutLogsError::InvalidRequest { ref message } if message == "Log events must be in chronological order."),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn maps_other_errors() {
+        let stand_in = StandIn::start(vec![(
+            400,
+            r#"{"__type":"AccessDeniedException","Message":"denied"}"#.to_string(),
+        )])
+        .await;
+
+        let err = client(&stand_in)
+            .put_logs(LogBatch::new(&dest(), &[], 0))
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "400 Bad Request AccessDeniedException: denied"
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn ensures_existing_destination() {
+        let already_exists =
+            r#"{"__type":"com.amazonaws.logs#ResourceAlreadyExistsException","message":"exists"}"#;
+        let stand_in = StandIn::start(vec![
+            (400, already_exists.to_string()),
+            (200, "{}".to_string()),
+        ])
+        .await;
+
+        client(&stand_in).ensure_destination(&dest()).await.unwrap();
+
+        let requests = stand_in.requests();
+        assert_eq!(
+            requests[0].header("x-amz-target"),
+            Some(CREATE_LOG_GROUP_TARGET)
+        );
+        assert_eq!(
+            requests[1].header("x-amz-target"),
+            Some(CREATE_LOG_STREAM_TARGET)
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(&requests[1].body).unwrap(),
+            json!({ "logGroupName": "group", "logStreamName": "stream" })
+        );
+    }
+}
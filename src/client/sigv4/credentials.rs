@@ -0,0 +1,397 @@
+use std::{path::PathBuf, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use hyper::{Body, Request};
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use crate::metadata::{HttpClient, IMDS_ENDPOINT, MetadataError, imds_get, imds_token, send};
+
+/// The default container credentials endpoint used with `AWS_CONTAINER_CREDENTIALS_RELATIVE_URI`.
+const CONTAINER_ENDPOINT: &str = "http://169.254.170.2";
+/// Refresh credentials this long before they expire.
+const EXPIRATION_BUFFER: chrono::TimeDelta = chrono::TimeDelta::minutes(5);
+
+/// AWS credentials used to sign requests.
+#[derive(Clone)]
+pub struct Credentials {
+    pub(crate) access_key_id: String,
+    pub(crate) secret_access_key: String,
+    pub(crate) session_token: Option<String>,
+    pub(crate) expiration: Option<DateTime<Utc>>,
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("access_key_id", &self.access_key_id)
+            .field("secret_access_key", &"** redacted **")
+            .field("expiration", &self.expiration)
+            .finish()
+    }
+}
+
+impl Credentials {
+    pub fn new(
+        access_key_id: impl Into<String>,
+        secret_access_key: impl Into<String>,
+        session_token: Option<String>,
+    ) -> Self {
+        Self {
+            access_key_id: access_key_id.into(),
+            secret_access_key: secret_access_key.into(),
+            session_token,
+            expiration: None,
+        }
+    }
+
+    fn is_fresh(&self) -> bool {
+        self.expiration
+            .is_none_or(|expiration| Utc::now() + EXPIRATION_BUFFER < expiration)
+    }
+
+    /// Parse the JSON document returned by the container and instance metadata endpoints.
+    fn from_metadata_json(body: &[u8]) -> Result<Self, CredentialsError> {
+        let value: Value = serde_json::from_slice(body)
+            .map_err(|err| CredentialsError::InvalidResponse(err.to_string()))?;
+        let field = |name: &str| value.get(name).and_then(Value::as_str).map(str::to_string);
+
+        let (Some(access_key_id), Some(secret_access_key)) =
+            (field("AccessKeyId"), field("SecretAccessKey"))
+        else {
+            return Err(CredentialsError::InvalidResponse(
+                "missing AccessKeyId or SecretAccessKey".to_string(),
+            ));
+        };
+
+        Ok(Self {
+            access_key_id,
+            secret_access_key,
+            session_token: field("Token"),
+            expiration: field("Expiration")
+                .and_then(|expiration| DateTime::parse_from_rfc3339(&expiration).ok())
+                .map(|expiration| expiration.with_timezone(&Utc)),
+        })
+    }
+}
+
+/// Errors raised while resolving [`Credentials`].
+#[derive(Debug, thiserror::Error)]
+pub enum CredentialsError {
+    #[error("no credentials found in env vars, profile file, container or instance metadata")]
+    NotFound,
+    #[error("failed to request credentials: {0}")]
+    Http(String),
+    #[error("invalid credentials response: {0}")]
+    InvalidResponse(String),
+}
+
+impl From<MetadataError> for CredentialsError {
+    fn from(err: MetadataError) -> Self {
+        match err {
+            MetadataError::Http(message) => Self::Http(message),
+            MetadataError::InvalidResponse(message) => Self::InvalidResponse(message),
+        }
+    }
+}
+
+/// Resolves [`Credentials`] and caches them until shortly before they expire.
+///
+/// The default chain tries, in order:
+///
+/// 1. `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`
+/// 2. the shared credentials file (`AWS_SHARED_CREDENTIALS_FILE` or `~/.aws/credentials`) with
+///    the `AWS_PROFILE` profile (`default` if unset)
+/// 3. the container credentials endpoint if `AWS_CONTAINER_CREDENTIALS_RELATIVE_URI` or
+///    `AWS_CONTAINER_CREDENTIALS_FULL_URI` is set
+/// 4. the instance metadata service (IMDSv2)
+#[derive(Clone)]
+pub struct CredentialsProvider {
+    source: Source,
+    container_endpoint: String,
+    imds_endpoint: String,
+    cache: Arc<Mutex<Option<Credentials>>>,
+}
+
+#[derive(Clone)]
+enum Source {
+    Static(Credentials),
+    Chain,
+}
+
+impl Default for CredentialsProvider {
+    fn default() -> Self {
+        Self::default_chain()
+    }
+}
+
+impl CredentialsProvider {
+    /// Resolve credentials with the default chain.
+    pub fn default_chain() -> Self {
+        Self::with_source(Source::Chain)
+    }
+
+    /// Always use the given credentials.
+    pub fn static_credentials(credentials: Credentials) -> Self {
+        Self::with_source(Source::Static(credentials))
+    }
+
+    fn with_source(source: Source) -> Self {
+        Self {
+            source,
+            container_endpoint: CONTAINER_ENDPOINT.to_string(),
+            imds_endpoint: IMDS_ENDPOINT.to_string(),
+            cache: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Set the endpoint used with `AWS_CONTAINER_CREDENTIALS_RELATIVE_URI`.
+    /// Default `http://169.254.170.2`.
+    pub fn with_container_endpoint(self, container_endpoint: impl Into<String>) -> Self {
+        Self {
+            container_endpoint: container_endpoint.into(),
+            ..self
+        }
+    }
+
+    /// Set the instance metadata service endpoint.
+    /// Default `http://169.254.169.254`.
+    pub fn with_imds_endpoint(self, imds_endpoint: impl Into<String>) -> Self {
+        Self {
+            imds_endpoint: imds_endpoint.into(),
+            ..self
+        }
+    }
+
+    pub(crate) async fn credentials(
+        &self,
+        http: &HttpClient,
+    ) -> Result<Credentials, CredentialsError> {
+        if let Source::Static(credentials) = &self.source {
+            return Ok(credentials.clone());
+        }
+
+        let mut cache = self.cache.lock().await;
+        if let Some(credentials) = cache.as_ref().filter(|credentials| credentials.is_fresh()) {
+            return Ok(credentials.clone());
+        }
+
+        let credentials = self.resolve(http, |name| std::env::var(name).ok()).await?;
+        *cache = Some(credentials.clone());
+        Ok(credentials)
+    }
+
+    async fn resolve(
+        &self,
+        http: &HttpClient,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Credentials, CredentialsError> {
+        if let Some(credentials) = from_env(&env) {
+            return Ok(credentials);
+        }
+
+        if let Some(path) = shared_credentials_path(&env) {
+            let profile = env("AWS_PROFILE").unwrap_or_else(|| "default".to_string());
+            if let Some(credentials) = std::fs::read_to_string(path)
+                .ok()
+                .and_then(|content| from_profile(&content, &profile))
+            {
+                return Ok(credentials);
+            }
+        }
+
+        if let Some(uri) = env("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI") {
+            let url = format!("{}{uri}", self.container_endpoint);
+            return self.container_credentials(http, &url, None).await;
+        }
+        if let Some(url) = env("AWS_CONTAINER_CREDENTIALS_FULL_URI") {
+            let token = env("AWS_CONTAINER_AUTHORIZATION_TOKEN");
+            return self.container_credentials(http, &url, token).await;
+        }
+
+        self.imds_credentials(http).await
+    }
+
+    async fn container_credentials(
+        &self,
+        http: &HttpClient,
+        url: &str,
+        token: Option<String>,
+    ) -> Result<Credentials, CredentialsError> {
+        let mut request = Request::get(url);
+        if let Some(token) = token {
+            request = request.header("authorization", token);
+        }
+        let body = send(http, request.body(Body::empty())).await?;
+        Credentials::from_metadata_json(&body)
+    }
+
+    async fn imds_credentials(&self, http: &HttpClient) -> Result<Credentials, CredentialsError> {
+        let base = &self.imds_endpoint;
+        let token = imds_token(http, base)
+            .await
+            .map_err(|_| CredentialsError::NotFound)?;
+
+        let path = format!("{base}/latest/meta-data/iam/security-credentials/");
+        let roles = imds_get(http, &path, &token).await?;
+        let role = roles
+            .lines()
+            .next()
+            .filter(|role| !role.is_empty())
+            .ok_or(CredentialsError::NotFound)?;
+
+        let body = imds_get(http, &format!("{path}{role}"), &token).await?;
+        Credentials::from_metadata_json(body.as_bytes())
+    }
+}
+
+fn from_env(env: impl Fn(&str) -> Option<String>) -> Option<Credentials> {
+    Some(Credentials::new(
+        env("AWS_ACCESS_KEY_ID")?,
+        env("AWS_SECRET_ACCESS_KEY")?,
+        env("AWS_SESSION_TOKEN"),
+    ))
+}
+
+fn shared_credentials_path(env: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    if let Some(path) = env("AWS_SHARED_CREDENTIALS_FILE") {
+        return Some(path.into());
+    }
+    let home = env("HOME").or_else(|| env("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".aws").join("credentials"))
+}
+
+/// Read the credentials of `profile` from the content of a shared credentials file.
+fn from_profile(content: &str, profile: &str) -> Option<Credentials> {
+    let mut in_profile = false;
+    let (mut access_key_id, mut secret_access_key, mut session_token) = (None, None, None);
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            in_profile = section.trim() == profile;
+            continue;
+        }
+        if !in_profile {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = Some(value.trim().to_string());
+        match key.trim() {
+            "aws_access_key_id" => access_key_id = value,
+            "aws_secret_access_key" => secret_access_key = value,
+            "aws_session_token" => session_token = value,
+            _ => {}
+        }
+    }
+
+    Some(Credentials::new(
+        access_key_id?,
+        secret_access_key?,
+        session_token,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{http_client, tests::StandIn};
+
+    #[test]
+    fn reads_profile() {
+        let content = "
+[default]
+aws_access_key_id = default-key
+aws_secret_access_key = default-secret
+
+[dev]
+# comment
+aws_access_key_id=dev-key
+aws_secret_access_key = dev-secret
+aws_session_token = dev-token
+";
+        let dev = from_profile(content, "dev").unwrap();
+        assert_eq!(dev.access_key_id, "dev-key");
+        assert_eq!(dev.secret_access_key, "dev-secret");
+        assert_eq!(dev.session_token.as_deref(), Some("dev-token"));
+
+        let default = from_profile(content, "default").unwrap();
+        assert_eq!(default.access_key_id, "default-key");
+        assert!(default.session_token.is_none());
+
+        assert!(from_profile(content, "missing").is_none());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn prefers_env_vars() {
+        let provider = CredentialsProvider::default_chain();
+        let credentials = provider
+            .resolve(&http_client(), |name| match name {
+                "AWS_ACCESS_KEY_ID" => Some("env-key".to_string()),
+                "AWS_SECRET_ACCESS_KEY" => Some("env-secret".to_string()),
+                _ => None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(credentials.access_key_id, "env-key");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn reads_container_endpoint() {
+        let stand_in = StandIn::start(vec![(
+            200,
+            r#"{"AccessKeyId":"container-key","SecretAccessKey":"container-secret","Token":"container-token","Expiration":"2100-01-01T00:00:00Z"}"#.to_string(),
+        )])
+        .await;
+        let provider = CredentialsProvider::default_chain().with_container_endpoint(stand_in.url());
+
+        let credentials = provider
+            .resolve(&http_client(), |name| {
+                (name == "AWS_CONTAINER_CREDENTIALS_RELATIVE_URI").then(|| "/v2/creds".to_string())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(credentials.access_key_id, "container-key");
+        assert_eq!(
+            credentials.session_token.as_deref(),
+            Some("container-token")
+        );
+        assert!(credentials.is_fresh());
+        assert_eq!(stand_in.requests()[0].path, "/v2/creds");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn reads_instance_metadata() {
+        let stand_in = StandIn::start(vec![
+            (200, "imds-token".to_string()),
+            (200, "my-role\n".to_string()),
+            (
+                200,
+                r#"{"AccessKeyId":"imds-key","SecretAccessKey":"imds-secret","Token":"imds-token"}"#
+                    .to_string(),
+            ),
+        ])
+        .await;
+        let provider = CredentialsProvider::default_chain().with_imds_endpoint(stand_in.url());
+
+        let credentials = provider.resolve(&http_client(), |_| None).await.unwrap();
+
+        assert_eq!(credentials.access_key_id, "imds-key");
+        let requests = stand_in.requests();
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].path, "/latest/api/token");
+        assert_eq!(
+            requests[2].path,
+            "/latest/meta-data/iam/security-credentials/my-role"
+        );
+        assert_eq!(
+            requests[2].header("x-aws-ec2-metadata-token"),
+            Some("imds-token")
+        );
+    }
+}
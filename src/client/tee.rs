@@ -0,0 +1,275 @@
+use std::{fmt, sync::Mutex};
+
+use futures_util::future::join_all;
+
+use crate::{
+    client::{BoxFuture, CloudWatchClient, LogBatch, PutLogsError},
+    export::LogDestination,
+};
+
+/// Which branches of a [`TeeClient`] must succeed for a batch to be considered sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TeePolicy {
+    /// Every branch must succeed.
+    ///
+    /// A batch retried by the [`Retry`](crate::middleware::Retry) layer is only sent again to
+    /// the branches which failed. Batches dead-lettered by the exporter hold every event, so
+    /// reingesting them sends duplicates to the branches which succeeded.
+    #[default]
+    All,
+    /// At least one branch must succeed.
+    Any,
+    /// The primary branch must succeed. Failures of other branches are only reported.
+    Primary,
+}
+
+/// A [`CloudWatchClient`] forwarding each batch to several clients concurrently,
+/// e.g. to ship the same logs to a second region or account.
+///
+/// The client passed to [`TeeClient::new`] is the primary branch with index 0,
+/// and branches added with [`TeeClient::with_branch`] follow in order.
+pub struct TeeClient {
+    branches: Vec<Box<dyn CloudWatchClient>>,
+    policy: TeePolicy,
+    /// Branches which accepted the last failed batch, skipped when it is retried.
+    last_failed: Mutex<Option<FailedBatch>>,
+}
+
+struct FailedBatch {
+    batch_id: u64,
+    sent: Vec<bool>,
+}
+
+impl TeeClient {
+    pub fn new(primary: impl CloudWatchClient + 'static) -> Self {
+        Self {
+            branches: vec![Box::new(primary)],
+            policy: TeePolicy::default(),
+            last_failed: Mutex::new(None),
+        }
+    }
+
+    /// Add a client receiving every batch.
+    pub fn with_branch(mut self, client: impl CloudWatchClient + 'static) -> Self {
+        self.branches.push(Box::new(client));
+        self
+    }
+
+    /// Set which branches must succeed. Default [`TeePolicy::All`].
+    pub fn with_policy(self, policy: TeePolicy) -> Self {
+        Self { policy, ..self }
+    }
+}
+
+impl CloudWatchClient for TeeClient {
+    fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        Box::pin(async move {
+            let sent = match self.last_failed.lock().unwrap().take() {
+                Some(failed)
+                    if batch.meta.attempt > 1 && failed.batch_id == batch.meta.batch_id =>
+                {
+                    failed.sent
+                }
+                _ => vec![false; self.branches.len()],
+            };
+            let results = join_all(self.branches.iter().zip(&sent).map(
+                |(client, &sent)| async move {
+                    if sent {
+                        return Ok(());
+                    }
+                    client.put_logs(batch).await
+                },
+            ))
+            .await;
+
+            let succeeded = match self.policy {
+                TeePolicy::All => results.iter().all(Result::is_ok),
+                TeePolicy::Any => results.iter().any(Result::is_ok),
+                TeePolicy::Primary => results[0].is_ok(),
+            };
+            let error = TeeError { results };
+
+            if !succeeded {
+                *self.last_failed.lock().unwrap() = Some(FailedBatch {
+                    batch_id: batch.meta.batch_id,
+                    sent: error.results.iter().map(Result::is_ok).collect(),
+                });
+                return Err(anyhow::Error::new(error).into());
+            }
+            if error.failed().next().is_some() {
+                eprintln!("[tracing-cloudwatch] {error}");
+            }
+            Ok(())
+        })
+    }
+
+    fn ensure_destination<'a>(
+        &'a self,
+        destination: &'a LogDestination,
+    ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+        Box::pin(async move {
+            let results = join_all(
+                self.branches
+                    .iter()
+                    .map(|client| client.ensure_destination(destination)),
+            )
+            .await;
+            results.into_iter().collect()
+        })
+    }
+}
+
+/// Error returned by [`TeeClient`] when the [`TeePolicy`] is not met,
+/// holding the result of each branch by index.
+///
+/// It is wrapped in [`PutLogsError::Other`] and can be recovered with `downcast_ref`.
+#[derive(Debug)]
+pub struct TeeError {
+    results: Vec<Result<(), PutLogsError>>,
+}
+
+impl TeeError {
+    /// Result of each branch, the primary branch first.
+    pub fn results(&self) -> &[Result<(), PutLogsError>] {
+        &self.results
+    }
+
+    fn failed(&self) -> impl Iterator<Item = (usize, &PutLogsError)> {
+        self.results
+            .iter()
+            .enumerate()
+            .filter_map(|(index, result)| result.as_ref().err().map(|err| (index, err)))
+    }
+}
+
+impl fmt::Display for TeeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} tee branches failed:",
+            self.failed().count(),
+            self.results.len()
+        )?;
+        for (index, err) in self.failed() {
+            write!(f, " [branch {index}] {err}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TeeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dispatch::LogEvent, export::LogDestination};
+    use chrono::Utc;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    struct Branch {
+        fail: bool,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl Branch {
+        fn new(fail: bool) -> (Self, Arc<AtomicUsize>) {
+            let calls = Arc::new(AtomicUsize::new(0));
+            (
+                Self {
+                    fail,
+                    calls: calls.clone(),
+                },
+                calls,
+            )
+        }
+    }
+
+    impl CloudWatchClient for Branch {
+        fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+            self.calls.fetch_add(batch.events.len(), Ordering::Relaxed);
+            let fail = self.fail;
+            Box::pin(async move {
+                if fail {
+                    Err(anyhow::anyhow!("unavailable").into())
+                } else {
+                    Ok(())
+                }
+            })
+        }
+    }
+
+    async fn put(client: &TeeClient) -> Result<(), PutLogsError> {
+        let events = [LogEvent::new("message", Utc::now())];
+        client
+            .put_logs(LogBatch::new(&LogDestination::default(), &events, 0))
+            .await
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn forwards_to_every_branch() {
+        let (primary, primary_calls) = Branch::new(false);
+        let (secondary, secondary_calls) = Branch::new(false);
+        let client = TeeClient::new(primary).with_branch(secondary);
+
+        put(&client).await.unwrap();
+
+        assert_eq!(primary_calls.load(Ordering::Relaxed), 1);
+        assert_eq!(secondary_calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn applies_policy() {
+        let tee = |primary_fails, secondary_fails, policy| {
+            TeeClient::new(Branch::new(primary_fails).0)
+                .with_branch(Branch::new(secondary_fails).0)
+                .with_policy(policy)
+        };
+
+        assert!(put(&tee(false, true, TeePolicy::All)).await.is_err());
+        assert!(put(&tee(false, true, TeePolicy::Any)).await.is_ok());
+        assert!(put(&tee(true, true, TeePolicy::Any)).await.is_err());
+        assert!(put(&tee(false, true, TeePolicy::Primary)).await.is_ok());
+        assert!(put(&tee(true, false, TeePolicy::Primary)).await.is_err());
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn retries_only_failed_branches() {
+        use crate::client::middleware::{
+            CloudWatchClientExt, Retry,
+            tests::{ScriptedClient, unavailable},
+        };
+
+        let primary = Arc::new(ScriptedClient::default());
+        let secondary = Arc::new(ScriptedClient::new(vec![unavailable()]));
+        let client = TeeClient::new(primary.clone())
+            .with_branch(secondary.clone())
+            .with(Retry::new(3));
+
+        crate::client::middleware::tests::put(&client)
+            .await
+            .unwrap();
+
+        assert_eq!(primary.calls(), 1);
+        assert_eq!(secondary.calls(), 2);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn reports_results_per_branch() {
+        let client = TeeClient::new(Branch::new(false).0).with_branch(Branch::new(true).0);
+
+        let PutLogsError::Other(err) = put(&client).await.unwrap_err() else {
+            panic!("expected tee error");
+        };
+        let err = err.downcast_ref::<TeeError>().unwrap();
+
+        assert!(err.results()[0].is_ok());
+        assert!(err.results()[1].is_err());
+        assert_eq!(
+            err.to_string(),
+            "1 of 2 tee branches failed: [branch 1] unavailable"
+        );
+    }
+}
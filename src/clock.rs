@@ -0,0 +1,35 @@
+use chrono::{DateTime, Utc};
+
+/// Source of event timestamps.
+///
+/// Implemented for closures, so a fixed clock can be injected in tests:
+///
+/// ```rust
+/// use chrono::{DateTime, Utc};
+///
+/// let clock = || DateTime::<Utc>::UNIX_EPOCH;
+/// let layer = tracing_cloudwatch::layer::<tracing_subscriber::Registry>().with_clock(clock);
+/// # let _ = layer;
+/// ```
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// [`Clock`] reading the system time. Default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+impl<F> Clock for F
+where
+    F: Fn() -> DateTime<Utc> + Send + Sync,
+{
+    fn now(&self) -> DateTime<Utc> {
+        self()
+    }
+}
@@ -0,0 +1,414 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing_core::Subscriber;
+use tracing_subscriber::{EnvFilter, Layer, fmt, registry::LookupSpan};
+
+use crate::{
+    CloudWatchClient, CloudWatchWorkerGuard, ConfigError, ExportConfig, LoadShedding,
+    OversizedEventPolicy, layer::layer,
+};
+
+/// Prefix of the environment variables read by [`CloudWatchConfig::from_env`].
+const ENV_PREFIX: &str = "TRACING_CLOUDWATCH_";
+
+/// A type-erased [`CloudWatchLayer`](crate::CloudWatchLayer) built from a [`CloudWatchConfig`].
+pub type BoxedLayer<S> = Box<dyn Layer<S> + Send + Sync + 'static>;
+
+/// Which formatter is used to render events.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormatKind {
+    /// [`fmt::format::Full`]
+    #[default]
+    Full,
+    /// [`fmt::format::Compact`]
+    Compact,
+    /// [`fmt::format::Json`]
+    Json,
+}
+
+/// Formatting options of the CloudWatch layer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayerConfig {
+    /// Display line number and filename. Default true.
+    pub code_location: bool,
+    /// Display target module. Default false.
+    pub target: bool,
+    /// The formatter used to render events.
+    pub format: FormatKind,
+    /// An [`EnvFilter`] directive applied to the layer only, e.g. `info,my_crate=debug`.
+    pub filter: Option<String>,
+}
+
+impl Default for LayerConfig {
+    fn default() -> Self {
+        Self {
+            code_location: true,
+            target: false,
+            format: FormatKind::default(),
+            filter: None,
+        }
+    }
+}
+
+impl LayerConfig {
+    /// Build a type-erased layer with the client.
+    ///
+    /// Fails if `export_config` is invalid, see [`ExportConfig::try_build`].
+    /// Like [`CloudWatchLayer::with_client`](crate::CloudWatchLayer::with_client), this must be
+    /// called within a tokio runtime.
+    pub fn build<S, C>(
+        &self,
+        client: C,
+        export_config: ExportConfig,
+    ) -> Result<(BoxedLayer<S>, CloudWatchWorkerGuard), ConfigError>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+        C: CloudWatchClient + Send + Sync + 'static,
+    {
+        let export_config = export_config.try_build()?;
+        let filter = self.filter.as_deref().map(EnvFilter::try_new).transpose()?;
+
+        let fmt_layer = fmt::layer()
+            .without_time()
+            .with_ansi(false)
+            .with_level(true)
+            .with_line_number(self.code_location)
+            .with_file(self.code_location)
+            .with_target(self.target);
+
+        let (layer, guard): (BoxedLayer<S>, _) = match self.format {
+            FormatKind::Full => {
+                let (layer, guard) = layer()
+                    .with_fmt_layer(fmt_layer)
+                    .with_client(client, export_config);
+                (layer.boxed(), guard)
+            }
+            FormatKind::Compact => {
+                let (layer, guard) = layer()
+                    .with_fmt_layer(fmt_layer.compact())
+                    .with_client(client, export_config);
+                (layer.boxed(), guard)
+            }
+            FormatKind::Json => {
+                let (layer, guard) = layer()
+                    .with_fmt_layer(fmt_layer.json())
+                    .with_client(client, export_config);
+                (layer.boxed(), guard)
+            }
+        };
+
+        let layer = match filter {
+            Some(filter) => layer.with_filter(filter).boxed(),
+            None => layer,
+        };
+
+        Ok((layer, guard))
+    }
+}
+
+/// Configurations of both the exporter and the layer, loadable from config files
+/// with serde or from `TRACING_CLOUDWATCH_*` environment variables.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CloudWatchConfig {
+    pub export: ExportConfig,
+    pub layer: LayerConfig,
+}
+
+impl CloudWatchConfig {
+    /// Load the default configuration overridden by environment variables.
+    ///
+    /// | Variable | Example |
+    /// |---|---|
+    /// | `TRACING_CLOUDWATCH_LOG_GROUP_NAME` | `my-group` |
+    /// | `TRACING_CLOUDWATCH_LOG_STREAM_NAME` | `my-stream` |
+    /// | `TRACING_CLOUDWATCH_BATCH_SIZE` | `100` |
+    /// | `TRACING_CLOUDWATCH_INTERVAL_MS` | `5000` |
+    /// | `TRACING_CLOUDWATCH_OVERSIZED_EVENT_POLICY` | `truncate`, `split` or `drop` |
+    /// | `TRACING_CLOUDWATCH_QUEUE_CAPACITY` | `10000` |
+    /// | `TRACING_CLOUDWATCH_RESOURCE` | `service=checkout,version=1.2.0` |
+    /// | `TRACING_CLOUDWATCH_FLUSH_LEVEL` | `error` |
+    /// | `TRACING_CLOUDWATCH_LOAD_SHEDDING` | `50,70,85`, the TRACE, DEBUG and INFO watermarks |
+    /// | `TRACING_CLOUDWATCH_SAMPLE_RATE` | `0.25` |
+    /// | `TRACING_CLOUDWATCH_CODE_LOCATION` | `true` |
+    /// | `TRACING_CLOUDWATCH_TARGET` | `false` |
+    /// | `TRACING_CLOUDWATCH_FORMAT` | `full`, `compact` or `json` |
+    /// | `TRACING_CLOUDWATCH_FILTER` | `info,my_crate=debug` |
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::default().merge_env(std::env::vars())
+    }
+
+    /// Override this configuration with `TRACING_CLOUDWATCH_*` variables.
+    /// Unknown variables are ignored.
+    pub fn merge_env<I, K, V>(mut self, vars: I) -> Result<Self, ConfigError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Into<String>,
+    {
+        for (name, value) in vars {
+            let name = name.as_ref();
+            let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            let value = value.into();
+            let invalid = |reason: &dyn std::fmt::Display| ConfigError::InvalidEnvVar {
+                name: name.to_string(),
+                value: value.clone(),
+                reason: reason.to_string(),
+            };
+
+            match key {
+                "LOG_GROUP_NAME" => self.export = self.export.with_log_group_name(&value),
+                "LOG_STREAM_NAME" => self.export = self.export.with_log_stream_name(&value),
+                "BATCH_SIZE" => {
+                    let batch_size: usize = value.parse().map_err(|err| invalid(&err))?;
+                    self.export = self
+                        .export
+                        .try_with_batch_size(batch_size)
+                        .map_err(|err| invalid(&err))?;
+                }
+                "INTERVAL_MS" => {
+                    let millis: u64 = value.parse().map_err(|err| invalid(&err))?;
+                    self.export = self.export.with_interval(Duration::from_millis(millis));
+                }
+                "OVERSIZED_EVENT_POLICY" => {
+                    let policy = match value.as_str() {
+                        "truncate" => OversizedEventPolicy::Truncate,
+                        "split" => OversizedEventPolicy::Split,
+                        "drop" => OversizedEventPolicy::Drop,
+                        _ => return Err(invalid(&"expected truncate, split or drop")),
+                    };
+                    self.export = self.export.with_oversized_event_policy(policy);
+                }
+                "QUEUE_CAPACITY" => {
+                    let capacity: usize = value.parse().map_err(|err| invalid(&err))?;
+                    self.export = self
+                        .export
+                        .try_with_queue_capacity(capacity)
+                        .map_err(|err| invalid(&err))?;
+                }
+                "RESOURCE" => {
+                    let fields = value
+                        .split(',')
+                        .filter(|field| !field.trim().is_empty())
+                        .map(|field| {
+                            field
+                                .split_once('=')
+                                .map(|(key, value)| (key.trim(), value.trim()))
+                                .ok_or_else(|| invalid(&"expected comma separated key=value pairs"))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    self.export = self.export.with_resource(fields);
+                }
+                "FLUSH_LEVEL" => {
+                    let level: tracing::Level = value.parse().map_err(|err| invalid(&err))?;
+                    self.export = self.export.with_flush_level(level);
+                }
+                "LOAD_SHEDDING" => {
+                    let percents = value
+                        .split(',')
+                        .map(|percent| percent.trim().parse::<u8>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|err| invalid(&err))?;
+                    let [trace, debug, info] = percents[..] else {
+                        return Err(invalid(&"expected three comma separated percentages"));
+                    };
+                    self.export = self.export.with_load_shedding(
+                        LoadShedding::new()
+                            .with_trace_watermark(trace)
+                            .with_debug_watermark(debug)
+                            .with_info_watermark(info),
+                    );
+                }
+                "SAMPLE_RATE" => {
+                    let sample_rate: f64 = value.parse().map_err(|err| invalid(&err))?;
+                    self.export = self.export.with_sample_rate(sample_rate);
+                }
+                "CODE_LOCATION" => {
+                    self.layer.code_location = value.parse().map_err(|err| invalid(&err))?
+                }
+                "TARGET" => self.layer.target = value.parse().map_err(|err| invalid(&err))?,
+                "FORMAT" => {
+                    self.layer.format = match value.as_str() {
+                        "full" => FormatKind::Full,
+                        "compact" => FormatKind::Compact,
+                        "json" => FormatKind::Json,
+                        _ => return Err(invalid(&"expected full, compact or json")),
+                    }
+                }
+                "FILTER" => self.layer.filter = Some(value),
+                _ => {}
+            }
+        }
+        Ok(self)
+    }
+
+    /// Build a type-erased layer with the client. See [`LayerConfig::build`].
+    pub fn build<S, C>(
+        self,
+        client: C,
+    ) -> Result<(BoxedLayer<S>, CloudWatchWorkerGuard), ConfigError>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+        C: CloudWatchClient + Send + Sync + 'static,
+    {
+        self.layer.build(client, self.export)
+    }
+}
+
+/// Build a type-erased layer and its guard from `TRACING_CLOUDWATCH_*` environment variables.
+///
+/// See [`CloudWatchConfig::from_env`] for the supported variables.
+pub fn init_from_env<S, C>(client: C) -> Result<(BoxedLayer<S>, CloudWatchWorkerGuard), ConfigError>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    C: CloudWatchClient + Send + Sync + 'static,
+{
+    CloudWatchConfig::from_env()?.build(client)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::NoopClient;
+    use tracing_subscriber::{Registry, layer::SubscriberExt};
+
+    #[test]
+    fn merges_env_vars() {
+        let config = CloudWatchConfig::default()
+            .merge_env([
+                ("TRACING_CLOUDWATCH_LOG_GROUP_NAME", "group"),
+                ("TRACING_CLOUDWATCH_LOG_STREAM_NAME", "stream"),
+                ("TRACING_CLOUDWATCH_BATCH_SIZE", "42"),
+                ("TRACING_CLOUDWATCH_INTERVAL_MS", "1500"),
+                ("TRACING_CLOUDWATCH_OVERSIZED_EVENT_POLICY", "split"),
+                ("TRACING_CLOUDWATCH_QUEUE_CAPACITY", "512"),
+                (
+                    "TRACING_CLOUDWATCH_RESOURCE",
+                    "service=checkout, version=1.2.0",
+                ),
+                ("TRACING_CLOUDWATCH_FLUSH_LEVEL", "ERROR"),
+                ("TRACING_CLOUDWATCH_LOAD_SHEDDING", "40, 60, 80"),
+                ("TRACING_CLOUDWATCH_SAMPLE_RATE", "0.25"),
+                ("TRACING_CLOUDWATCH_CODE_LOCATION", "false"),
+                ("TRACING_CLOUDWATCH_FORMAT", "json"),
+                ("TRACING_CLOUDWATCH_FILTER", "info"),
+                ("UNRELATED", "ignored"),
+            ])
+            .unwrap();
+
+        let value = serde_json::to_value(&config).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "export": {
+                    "batch_size": 42,
+                    "interval_ms": 1500,
+                    "destination": {
+                        "log_group_name": "group",
+                        "log_stream_name": "stream",
+                    },
+                    "oversized_event_policy": "split",
+                    "queue_capacity": 512,
+                    "resource": {
+                        "service": "checkout",
+                        "version": "1.2.0",
+                    },
+                    "circuit_breaker_threshold": 0,
+                    "circuit_breaker_cooldown_ms": 30000,
+                    "flush_level": "error",
+                    "sample_rate": 0.25,
+                    "load_shedding": {
+                        "trace_percent": 40,
+                        "debug_percent": 60,
+                        "info_percent": 80,
+                    },
+                },
+                "layer": {
+                    "code_location": false,
+                    "target": false,
+                    "format": "json",
+                    "filter": "info",
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_env_vars() {
+        for (name, value) in [
+            ("TRACING_CLOUDWATCH_BATCH_SIZE", "0"),
+            ("TRACING_CLOUDWATCH_BATCH_SIZE", "10001"),
+            ("TRACING_CLOUDWATCH_INTERVAL_MS", "soon"),
+            ("TRACING_CLOUDWATCH_FORMAT", "xml"),
+            ("TRACING_CLOUDWATCH_FLUSH_LEVEL", "fatal"),
+            ("TRACING_CLOUDWATCH_LOAD_SHEDDING", "50,70"),
+            ("TRACING_CLOUDWATCH_SAMPLE_RATE", "half"),
+            ("TRACING_CLOUDWATCH_RESOURCE", "service"),
+        ] {
+            let err = CloudWatchConfig::default()
+                .merge_env([(name, value)])
+                .unwrap_err();
+            assert!(
+                matches!(err, ConfigError::InvalidEnvVar { name: ref n, .. } if n == name),
+                "{err}"
+            );
+        }
+    }
+
+    #[test]
+    fn deserializes_partial_config() {
+        let config: CloudWatchConfig = serde_json::from_value(serde_json::json!({
+            "export": { "destination": { "log_group_name": "group" } },
+            "layer": { "format": "compact" },
+        }))
+        .unwrap();
+
+        assert_eq!(config.layer.format, FormatKind::Compact);
+        assert!(config.layer.code_location);
+        let value = serde_json::to_value(&config.export).unwrap();
+        assert_eq!(value["destination"]["log_group_name"], "group");
+        assert_eq!(value["batch_size"], 5);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn builds_layer_for_every_format() {
+        for format in [FormatKind::Full, FormatKind::Compact, FormatKind::Json] {
+            let config = CloudWatchConfig {
+                layer: LayerConfig {
+                    format,
+                    filter: Some("info".to_string()),
+                    ..Default::default()
+                },
+                export: ExportConfig::default()
+                    .with_log_group_name("group")
+                    .with_log_stream_name("stream"),
+            };
+            let (layer, guard) = config.build::<Registry, _>(NoopClient::new()).unwrap();
+            let subscriber = tracing_subscriber::registry().with(layer);
+            tracing::subscriber::with_default(subscriber, || tracing::info!("hello"));
+            guard.shutdown().await;
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_filter() {
+        let config = CloudWatchConfig::default()
+            .merge_env([
+                ("TRACING_CLOUDWATCH_LOG_GROUP_NAME", "group"),
+                ("TRACING_CLOUDWATCH_LOG_STREAM_NAME", "stream"),
+                ("TRACING_CLOUDWATCH_FILTER", "[["),
+            ])
+            .unwrap();
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let _enter = rt.enter();
+        let err = config.build::<Registry, _>(NoopClient::new()).err();
+        assert!(matches!(err, Some(ConfigError::InvalidFilter(_))));
+    }
+}
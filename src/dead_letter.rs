@@ -0,0 +1,336 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use chrono::DateTime;
+use serde_json::{Value, json};
+
+use crate::{
+    batch,
+    client::{CloudWatchClient, LogBatch, PutLogsError},
+    dispatch::LogEvent,
+    export::{ExportError, LogDestination},
+};
+
+/// A batch which could not be exported.
+#[derive(Debug, Clone, Copy)]
+pub struct DeadLetter<'a> {
+    pub destination: &'a LogDestination,
+    pub events: &'a [LogEvent],
+    /// Why the batch was not exported.
+    pub error: &'a ExportError,
+}
+
+/// Receives batches which could not be exported, set with
+/// [`ExportConfig::with_dead_letter`](crate::ExportConfig::with_dead_letter).
+///
+/// Implemented for closures:
+///
+/// ```rust
+/// let config = tracing_cloudwatch::ExportConfig::default().with_dead_letter(
+///     |dead_letter: tracing_cloudwatch::DeadLetter<'_>| {
+///         eprintln!("lost {} events: {}", dead_letter.events.len(), dead_letter.error);
+///     },
+/// );
+/// # let _ = config;
+/// ```
+pub trait DeadLetterSink: Send + Sync {
+    fn dead_letter(&self, dead_letter: DeadLetter<'_>);
+}
+
+impl<F> DeadLetterSink for F
+where
+    F: Fn(DeadLetter<'_>) + Send + Sync,
+{
+    fn dead_letter(&self, dead_letter: DeadLetter<'_>) {
+        self(dead_letter)
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct DeadLetterHandler(Arc<dyn DeadLetterSink>);
+
+impl DeadLetterHandler {
+    pub(crate) fn new(sink: impl DeadLetterSink + 'static) -> Self {
+        Self(Arc::new(sink))
+    }
+
+    pub(crate) fn send(&self, dead_letter: DeadLetter<'_>) {
+        self.0.dead_letter(dead_letter)
+    }
+}
+
+impl std::fmt::Debug for DeadLetterHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DeadLetterHandler")
+    }
+}
+
+/// [`DeadLetterSink`] appending events to a JSON Lines file, one event per line:
+///
+/// ```json
+/// {"log_group_name":"group","log_stream_name":"stream","timestamp":1700000000000,"message":"...","error":"..."}
+/// ```
+///
+/// The file can be sent to CloudWatch later with [`JsonlDeadLetter::reingest`].
+pub struct JsonlDeadLetter {
+    path: PathBuf,
+    file: Mutex<Option<BufWriter<File>>>,
+}
+
+impl JsonlDeadLetter {
+    /// The file is created on the first dead letter.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            file: Mutex::new(None),
+        }
+    }
+
+    fn append(&self, dead_letter: DeadLetter<'_>) -> std::io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        if file.is_none() {
+            let opened = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            *file = Some(BufWriter::new(opened));
+        }
+        let file = file.as_mut().expect("dead letter file is open");
+
+        let error = dead_letter.error.to_string();
+        for event in dead_letter.events {
+            let line = json!({
+                "log_group_name": dead_letter.destination.log_group_name,
+                "log_stream_name": dead_letter.destination.log_stream_name,
+                "timestamp": event.timestamp.timestamp_millis(),
+                "message": event.message,
+                "error": error,
+            });
+            writeln!(file, "{line}")?;
+        }
+        file.flush()
+    }
+
+    /// Send the events of a dead letter file with `client`, in batches per destination.
+    ///
+    /// The events of each destination are sorted by timestamp and sent in batches within the
+    /// `PutLogEvents` limits, like the exporter does.
+    ///
+    /// Returns the number of events sent. The file is left untouched, so it can be removed once
+    /// this succeeds. If a batch fails, the events of the previous batches have been sent.
+    pub async fn reingest<C>(path: impl AsRef<Path>, client: &C) -> Result<usize, ReingestError>
+    where
+        C: CloudWatchClient + ?Sized,
+    {
+        let reader = BufReader::new(File::open(path)?);
+        let mut destinations: Vec<(LogDestination, Vec<LogEvent>)> = Vec::new();
+
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (line_destination, event) =
+                parse_line(&line).ok_or(ReingestError::InvalidLine(index + 1))?;
+
+            let same_destination = |(destination, _): &&mut (LogDestination, Vec<LogEvent>)| {
+                line_destination.log_group_name == destination.log_group_name
+                    && line_destination.log_stream_name == destination.log_stream_name
+            };
+            match destinations.iter_mut().find(same_destination) {
+                Some((_, events)) => events.push(event),
+                None => destinations.push((line_destination, vec![event])),
+            }
+        }
+
+        let mut batch_id = 0;
+        let mut sent = 0;
+        for (destination, mut events) in destinations {
+            events.sort_by_key(|event| event.timestamp);
+            let mut remaining = &events[..];
+            while !remaining.is_empty() {
+                let (batch, rest) = remaining.split_at(batch::batch_len(remaining));
+                client
+                    .put_logs(LogBatch::new(&destination, batch, batch_id))
+                    .await?;
+                batch_id += 1;
+                sent += batch.len();
+                remaining = rest;
+            }
+        }
+
+        Ok(sent)
+    }
+}
+
+impl DeadLetterSink for JsonlDeadLetter {
+    fn dead_letter(&self, dead_letter: DeadLetter<'_>) {
+        if let Err(err) = self.append(dead_letter) {
+            eprintln!(
+                "[tracing-cloudwatch] Unable to write {} events to the dead letter file {}: {err}",
+                dead_letter.events.len(),
+                self.path.display()
+            );
+        }
+    }
+}
+
+fn parse_line(line: &str) -> Option<(LogDestination, LogEvent)> {
+    let value: Value = serde_json::from_str(line).ok()?;
+    let destination = LogDestination {
+        log_group_name: value["log_group_name"].as_str()?.to_string(),
+        log_stream_name: value["log_stream_name"].as_str()?.to_string(),
+    };
+    let event = LogEvent::new(
+        value["message"].as_str()?.to_string(),
+        DateTime::from_timestamp_millis(value["timestamp"].as_i64()?)?,
+    );
+    Some((destination, event))
+}
+
+/// Error of [`JsonlDeadLetter::reingest`].
+#[derive(Debug, thiserror::Error)]
+pub enum ReingestError {
+    #[error("unable to read the dead letter file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid dead letter on line {0}")]
+    InvalidLine(usize),
+    #[error(transparent)]
+    PutLogs(#[from] PutLogsError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::BoxFuture;
+    use chrono::{TimeDelta, Utc};
+
+    #[derive(Default)]
+    struct RecordingClient {
+        batches: Mutex<Vec<(String, Vec<String>)>>,
+    }
+
+    impl CloudWatchClient for RecordingClient {
+        fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+            let messages = batch.events.iter().map(|event| event.message.clone());
+            self.batches.lock().unwrap().push((
+                batch.destination.log_stream_name.clone(),
+                messages.collect(),
+            ));
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn destination(stream: &str) -> LogDestination {
+        LogDestination {
+            log_group_name: "group".to_string(),
+            log_stream_name: stream.to_string(),
+        }
+    }
+
+    fn events(messages: &[&str]) -> Vec<LogEvent> {
+        messages
+            .iter()
+            .map(|&message| LogEvent::new(message, Utc::now()))
+            .collect()
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn reingests_written_dead_letters() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dead.jsonl");
+        let sink = JsonlDeadLetter::new(&path);
+        let error = ExportError::CircuitOpen;
+
+        for (stream, messages) in [("a", &["1", "2"][..]), ("b", &["3"][..])] {
+            sink.dead_letter(DeadLetter {
+                destination: &destination(stream),
+                events: &events(messages),
+                error: &error,
+            });
+        }
+
+        let client = RecordingClient::default();
+        let sent = JsonlDeadLetter::reingest(&path, &client).await.unwrap();
+
+        assert_eq!(sent, 3);
+        assert_eq!(
+            *client.batches.lock().unwrap(),
+            [
+                ("a".to_string(), vec!["1".to_string(), "2".to_string()]),
+                ("b".to_string(), vec!["3".to_string()]),
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn reingests_in_sorted_batches_within_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dead.jsonl");
+        let sink = JsonlDeadLetter::new(&path);
+        let error = ExportError::CircuitOpen;
+        let start = Utc::now();
+        let large = "x".repeat(300 * 1024);
+
+        for (stream, message, hours) in [
+            ("a", "late", 25),
+            ("b", "other", 0),
+            ("a", "early", 0),
+            ("a", "mid", 1),
+        ] {
+            sink.dead_letter(DeadLetter {
+                destination: &destination(stream),
+                events: &[LogEvent::new(message, start + TimeDelta::hours(hours))],
+                error: &error,
+            });
+        }
+        sink.dead_letter(DeadLetter {
+            destination: &destination("c"),
+            events: &vec![LogEvent::new(large.as_str(), start); 5],
+            error: &error,
+        });
+
+        let client = RecordingClient::default();
+        let sent = JsonlDeadLetter::reingest(&path, &client).await.unwrap();
+
+        assert_eq!(sent, 9);
+        let batches = client.batches.lock().unwrap();
+        let batches: Vec<_> = batches
+            .iter()
+            .map(|(stream, messages)| {
+                let messages = messages.iter().map(|message| match message.len() {
+                    len if len > 100 => "large",
+                    _ => message.as_str(),
+                });
+                (stream.as_str(), messages.collect::<Vec<_>>())
+            })
+            .collect();
+        assert_eq!(
+            batches,
+            [
+                ("a", vec!["early", "mid"]),
+                ("a", vec!["late"]),
+                ("b", vec!["other"]),
+                ("c", vec!["large"; 3]),
+                ("c", vec!["large"; 2]),
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn rejects_invalid_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dead.jsonl");
+        std::fs::write(&path, "{\"message\":\"no destination\"}\n").unwrap();
+
+        let err = JsonlDeadLetter::reingest(&path, &RecordingClient::default())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, ReingestError::InvalidLine(1)), "{err}");
+    }
+}
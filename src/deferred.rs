@@ -0,0 +1,356 @@
+use std::{fmt::Write as _, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+use tracing_core::{
+    Event, Field, Level, Metadata, Subscriber,
+    field::Visit,
+    span::{self, Attributes, Record},
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan};
+
+/// Formats events captured with [`CloudWatchLayer::with_deferred_formatting`] on the exporter task.
+///
+/// Implemented for closures, so redaction or enrichment can wrap another formatter:
+///
+/// ```rust
+/// use tracing_cloudwatch::{EventFormatter, EventRecord, JsonFormatter};
+///
+/// let json = JsonFormatter::default();
+/// let formatter = move |record: &EventRecord, buf: &mut String| {
+///     json.format(record, buf);
+///     *buf = buf.replace("hunter2", "***");
+/// };
+/// let layer = tracing_cloudwatch::layer::<tracing_subscriber::Registry>()
+///     .with_deferred_formatting(formatter);
+/// # let _ = layer;
+/// ```
+///
+/// [`CloudWatchLayer::with_deferred_formatting`]: crate::CloudWatchLayer::with_deferred_formatting
+pub trait EventFormatter: Send + Sync {
+    /// Append the message of `record` to `buf`.
+    fn format(&self, record: &EventRecord, buf: &mut String);
+}
+
+impl<F> EventFormatter for F
+where
+    F: Fn(&EventRecord, &mut String) + Send + Sync,
+{
+    fn format(&self, record: &EventRecord, buf: &mut String) {
+        self(record, buf)
+    }
+}
+
+/// Value of a captured field.
+///
+/// `Debug` values are formatted on the application thread, since they are borrowed by the event.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Str(String),
+    Debug(String),
+}
+
+impl From<&FieldValue> for Value {
+    fn from(value: &FieldValue) -> Self {
+        match value {
+            FieldValue::Bool(value) => (*value).into(),
+            FieldValue::I64(value) => (*value).into(),
+            FieldValue::U64(value) => (*value).into(),
+            FieldValue::F64(value) => (*value).into(),
+            FieldValue::Str(value) | FieldValue::Debug(value) => value.as_str().into(),
+        }
+    }
+}
+
+type Fields = Vec<(&'static str, FieldValue)>;
+
+/// Span in the scope of a captured event.
+#[derive(Debug, Clone)]
+pub struct SpanContext {
+    id: u64,
+    name: &'static str,
+    fields: Arc<Fields>,
+}
+
+impl SpanContext {
+    /// A span without fields, e.g. to build an [`EventRecord`] in tests of an [`EventFormatter`].
+    pub fn new(id: u64, name: &'static str) -> Self {
+        Self {
+            id,
+            name,
+            fields: Arc::default(),
+        }
+    }
+
+    /// Add a field to the span.
+    pub fn with_field(mut self, name: &'static str, value: FieldValue) -> Self {
+        Arc::make_mut(&mut self.fields).push((name, value));
+        self
+    }
+
+    /// The ID of the span in the subscriber, unique among the spans open at the same time.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn fields(&self) -> &[(&'static str, FieldValue)] {
+        &self.fields
+    }
+}
+
+/// Owned copy of an event and its span scope, cheap to capture on the application thread.
+#[derive(Debug, Clone)]
+pub struct EventRecord {
+    timestamp: DateTime<Utc>,
+    metadata: &'static Metadata<'static>,
+    fields: Fields,
+    spans: Vec<SpanContext>,
+}
+
+impl EventRecord {
+    /// An event without fields or spans, e.g. to test an [`EventFormatter`].
+    pub fn new(timestamp: DateTime<Utc>, metadata: &'static Metadata<'static>) -> Self {
+        Self {
+            timestamp,
+            metadata,
+            fields: Vec::new(),
+            spans: Vec::new(),
+        }
+    }
+
+    /// Add a field to the event.
+    pub fn with_field(mut self, name: &'static str, value: FieldValue) -> Self {
+        self.fields.push((name, value));
+        self
+    }
+
+    /// Add a span to the scope of the event, below the spans added before.
+    pub fn with_span(mut self, span: SpanContext) -> Self {
+        self.spans.push(span);
+        self
+    }
+
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    pub fn level(&self) -> &Level {
+        self.metadata.level()
+    }
+
+    pub fn target(&self) -> &'static str {
+        self.metadata.target()
+    }
+
+    /// Metadata of the callsite, e.g. for the file and line.
+    pub fn metadata(&self) -> &'static Metadata<'static> {
+        self.metadata
+    }
+
+    /// Fields of the event, including `message`.
+    pub fn fields(&self) -> &[(&'static str, FieldValue)] {
+        &self.fields
+    }
+
+    /// Spans the event happened in, from the root.
+    pub fn spans(&self) -> &[SpanContext] {
+        &self.spans
+    }
+}
+
+/// [`EventFormatter`] writing each event as a JSON object. Default.
+///
+/// ```json
+/// {"fields":{"message":"Hello!","user":"a"},"filename":"src/main.rs","level":"INFO","line_number":12,"spans":[{"name":"handle","request_id":1}]}
+/// ```
+#[derive(Debug, Clone)]
+pub struct JsonFormatter {
+    code_location: bool,
+    target: bool,
+}
+
+impl Default for JsonFormatter {
+    fn default() -> Self {
+        Self {
+            code_location: true,
+            target: false,
+        }
+    }
+}
+
+impl JsonFormatter {
+    /// Configure to display line number and filename.
+    /// Default true
+    pub fn with_code_location(self, display: bool) -> Self {
+        Self {
+            code_location: display,
+            ..self
+        }
+    }
+
+    /// Configure to display target module.
+    /// Default false.
+    pub fn with_target(self, display: bool) -> Self {
+        Self {
+            target: display,
+            ..self
+        }
+    }
+}
+
+impl EventFormatter for JsonFormatter {
+    fn format(&self, record: &EventRecord, buf: &mut String) {
+        let mut object = Map::new();
+        object.insert("level".into(), record.level().as_str().into());
+        object.insert("fields".into(), fields_object(record.fields()));
+        if self.target {
+            object.insert("target".into(), record.target().into());
+        }
+        if self.code_location {
+            if let Some(file) = record.metadata.file() {
+                object.insert("filename".into(), file.into());
+            }
+            if let Some(line) = record.metadata.line() {
+                object.insert("line_number".into(), line.into());
+            }
+        }
+        if !record.spans.is_empty() {
+            let spans = record
+                .spans
+                .iter()
+                .map(|span| {
+                    let mut object = Map::new();
+                    object.insert("name".into(), span.name.into());
+                    for (name, value) in span.fields.iter() {
+                        object.insert((*name).into(), value.into());
+                    }
+                    Value::Object(object)
+                })
+                .collect();
+            object.insert("spans".into(), Value::Array(spans));
+        }
+        let _ = write!(buf, "{}", Value::Object(object));
+    }
+}
+
+fn fields_object(fields: &[(&'static str, FieldValue)]) -> Value {
+    Value::Object(
+        fields
+            .iter()
+            .map(|(name, value)| ((*name).into(), value.into()))
+            .collect(),
+    )
+}
+
+/// Fields of a span, stored in its extensions for the [`SpanContext`]s of its events while
+/// structured events or deferred formatting are enabled.
+struct DeferredSpanFields(Arc<Fields>);
+
+pub(crate) fn on_new_span<S>(attrs: &Attributes<'_>, id: &span::Id, ctx: &Context<'_, S>)
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let Some(span) = ctx.span(id) else {
+        return;
+    };
+    let mut fields = Vec::new();
+    attrs.record(&mut FieldVisitor(&mut fields));
+    span.extensions_mut()
+        .insert(DeferredSpanFields(Arc::new(fields)));
+}
+
+pub(crate) fn on_record<S>(id: &span::Id, values: &Record<'_>, ctx: &Context<'_, S>)
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let Some(span) = ctx.span(id) else {
+        return;
+    };
+    if let Some(DeferredSpanFields(fields)) = span.extensions_mut().get_mut() {
+        values.record(&mut FieldVisitor(Arc::make_mut(fields)));
+    }
+}
+
+/// Capture `event` and the fields of its spans without formatting them.
+pub(crate) fn capture_event<S>(
+    event: &Event<'_>,
+    ctx: &Context<'_, S>,
+    timestamp: DateTime<Utc>,
+) -> EventRecord
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let mut fields = Vec::with_capacity(event.metadata().fields().len());
+    event.record(&mut FieldVisitor(&mut fields));
+
+    let spans = ctx
+        .event_scope(event)
+        .map(|scope| {
+            scope
+                .from_root()
+                .map(|span| SpanContext {
+                    id: span.id().into_u64(),
+                    name: span.name(),
+                    fields: span
+                        .extensions()
+                        .get::<DeferredSpanFields>()
+                        .map(|DeferredSpanFields(fields)| fields.clone())
+                        .unwrap_or_default(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    EventRecord {
+        timestamp,
+        metadata: event.metadata(),
+        fields,
+        spans,
+    }
+}
+
+/// Record fields into owned values, replacing values recorded earlier for the same field.
+struct FieldVisitor<'a>(&'a mut Fields);
+
+impl FieldVisitor<'_> {
+    fn insert(&mut self, field: &Field, value: FieldValue) {
+        match self.0.iter_mut().find(|(name, _)| *name == field.name()) {
+            Some((_, existing)) => *existing = value,
+            None => self.0.push((field.name(), value)),
+        }
+    }
+}
+
+impl Visit for FieldVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, FieldValue::F64(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, FieldValue::I64(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, FieldValue::U64(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, FieldValue::Bool(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, FieldValue::Str(value.to_string()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.insert(field, FieldValue::Debug(format!("{value:?}")));
+    }
+}
@@ -1,24 +1,253 @@
 use crate::{
-    CloudWatchClient,
-    export::{BatchExporter, ExportConfig},
-    guard::ShutdownSignal,
+    deferred::{EventFormatter, EventRecord, FieldValue, SpanContext},
+    queue::{self, EventSender},
 };
 
+use std::{cell::RefCell, sync::Arc};
+
 use chrono::{DateTime, Utc};
-use tokio::sync::{
-    mpsc::{self, UnboundedSender},
-    oneshot,
-};
-use tracing::instrument::WithSubscriber;
+use tracing_core::{Level, Metadata};
 
 pub trait Dispatcher {
     fn dispatch(&self, input: LogEvent);
+
+    /// Dispatch an event captured with deferred formatting.
+    ///
+    /// By default the event is formatted on the calling thread.
+    fn dispatch_deferred(&self, record: EventRecord, formatter: &Arc<dyn EventFormatter>) {
+        self.dispatch(LogEvent::encode(record, formatter.as_ref()));
+    }
 }
 
-#[derive(Debug)]
+/// An event exported to CloudWatch: the message sent as is, and what is known about the event
+/// it was formatted from.
+///
+/// Events of [`CloudWatchLayer`](crate::CloudWatchLayer) carry the metadata of their callsite,
+/// so clients can route or sample them by level or target. With
+/// [`with_structured_events`](crate::CloudWatchLayer::with_structured_events) or
+/// [`with_deferred_formatting`](crate::CloudWatchLayer::with_deferred_formatting) they also carry
+/// their [`EventRecord`] with the fields and spans, so a client can encode them itself:
+///
+/// ```rust
+/// use tracing_cloudwatch::{EventFormatter, JsonFormatter, LogEvent};
+///
+/// fn encode(event: &LogEvent) -> String {
+///     match event.record() {
+///         Some(record) => {
+///             let mut message = String::new();
+///             JsonFormatter::default().with_target(true).format(record, &mut message);
+///             message
+///         }
+///         None => event.message.clone(),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
 pub struct LogEvent {
+    /// The message sent to CloudWatch.
     pub message: String,
     pub timestamp: DateTime<Utc>,
+    level: Option<Level>,
+    metadata: Option<&'static Metadata<'static>>,
+    record: Option<Arc<EventRecord>>,
+}
+
+impl LogEvent {
+    /// An event made of a message only, e.g. a span lifecycle record or a replayed message.
+    pub fn new(message: impl Into<String>, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            message: message.into(),
+            timestamp,
+            level: None,
+            metadata: None,
+            record: None,
+        }
+    }
+
+    /// Encode `record` into the message of a new event carrying it.
+    pub fn encode(record: EventRecord, encoder: &dyn EventFormatter) -> Self {
+        let mut message = queue::take_message_buffer();
+        encoder.format(&record, &mut message);
+        Self {
+            message,
+            timestamp: record.timestamp(),
+            level: Some(*record.level()),
+            metadata: Some(record.metadata()),
+            record: Some(Arc::new(record)),
+        }
+    }
+
+    /// Set the metadata of the callsite the event was emitted from.
+    pub fn with_metadata(self, metadata: &'static Metadata<'static>) -> Self {
+        Self {
+            level: Some(*metadata.level()),
+            metadata: Some(metadata),
+            ..self
+        }
+    }
+
+    /// Attach the captured event the message was formatted from, keeping the message.
+    pub(crate) fn with_record(self, record: EventRecord) -> Self {
+        Self {
+            level: Some(*record.level()),
+            metadata: Some(record.metadata()),
+            record: Some(Arc::new(record)),
+            ..self
+        }
+    }
+
+    /// Set the level of an event whose callsite metadata is not `'static`.
+    pub(crate) fn with_level(self, level: Level) -> Self {
+        Self {
+            level: Some(level),
+            ..self
+        }
+    }
+
+    /// An event with another message and the same timestamp, metadata and record.
+    pub(crate) fn with_message(&self, message: String) -> Self {
+        Self {
+            message,
+            timestamp: self.timestamp,
+            level: self.level,
+            metadata: self.metadata,
+            record: self.record.clone(),
+        }
+    }
+
+    /// The metadata of the callsite, `None` for span lifecycle records and other writes.
+    pub fn metadata(&self) -> Option<&'static Metadata<'static>> {
+        self.metadata
+    }
+
+    /// The level of the event, also set for events of a
+    /// [`CloudWatchMakeWriter`](crate::CloudWatchMakeWriter) which carry no metadata.
+    pub fn level(&self) -> Option<Level> {
+        self.level
+    }
+
+    pub fn target(&self) -> Option<&'static str> {
+        self.metadata.map(Metadata::target)
+    }
+
+    pub fn module_path(&self) -> Option<&'static str> {
+        self.metadata.and_then(Metadata::module_path)
+    }
+
+    pub fn file(&self) -> Option<&'static str> {
+        self.metadata.and_then(Metadata::file)
+    }
+
+    pub fn line(&self) -> Option<u32> {
+        self.metadata.and_then(Metadata::line)
+    }
+
+    /// The captured event, with structured events or deferred formatting of the
+    /// [`CloudWatchLayer`](crate::CloudWatchLayer), and for [`LogEvent::encode`].
+    pub fn record(&self) -> Option<&EventRecord> {
+        self.record.as_deref()
+    }
+
+    /// Fields of the event in the order they were recorded, empty without a record.
+    pub fn fields(&self) -> &[(&'static str, FieldValue)] {
+        self.record().map(EventRecord::fields).unwrap_or_default()
+    }
+
+    /// Spans the event happened in, from the root, empty without a record.
+    pub fn spans(&self) -> &[SpanContext] {
+        self.record().map(EventRecord::spans).unwrap_or_default()
+    }
+}
+
+tokio::task_local! {
+    /// Queue of the exporter task being polled. Events it emits through its diagnostics
+    /// dispatch are dropped by its own dispatcher, so it never exports itself.
+    pub(crate) static EXPORTER_QUEUE: usize;
+}
+
+thread_local! {
+    /// The event currently being formatted on this thread.
+    static PENDING_EVENT: RefCell<Option<PendingEvent>> = const { RefCell::new(None) };
+}
+
+struct PendingEvent {
+    /// Address of the dispatcher the event belongs to.
+    owner: usize,
+    buf: Vec<u8>,
+    timestamp: DateTime<Utc>,
+}
+
+/// Restores the outer pending event, even if formatting panics.
+struct PendingEventScope {
+    outer: Option<Option<PendingEvent>>,
+}
+
+impl PendingEventScope {
+    fn take(mut self) -> Option<PendingEvent> {
+        let outer = self.outer.take().flatten();
+        PENDING_EVENT.with(|pending| pending.replace(outer))
+    }
+}
+
+impl Drop for PendingEventScope {
+    fn drop(&mut self) {
+        if let Some(outer) = self.outer.take() {
+            PENDING_EVENT.with(|pending| pending.replace(outer));
+        }
+    }
+}
+
+/// Run `f` while collecting everything written to `dispatcher` on this thread into
+/// a single event stamped with `timestamp`.
+///
+/// Formatters may call `write` several times for one event, so the writes are joined
+/// here instead of being dispatched one by one.
+pub(crate) fn capture_event<D: ?Sized>(
+    dispatcher: &D,
+    timestamp: DateTime<Utc>,
+    f: impl FnOnce(),
+) -> Option<LogEvent> {
+    let pending = PendingEvent {
+        owner: owner_addr(dispatcher),
+        buf: queue::take_message_buffer().into_bytes(),
+        timestamp,
+    };
+    let scope = PendingEventScope {
+        outer: Some(PENDING_EVENT.with(|cell| cell.replace(Some(pending)))),
+    };
+
+    f();
+
+    let pending = scope.take()?;
+    let message = String::from_utf8(pending.buf)
+        .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned());
+    if message.is_empty() {
+        queue::recycle_message(message);
+        return None;
+    }
+    Some(LogEvent::new(message, pending.timestamp))
+}
+
+/// Write `buf` to the event captured for `dispatcher`, or dispatch it as its own event
+/// if the write happens outside of [`capture_event`].
+pub(crate) fn write_event<D: Dispatcher + ?Sized>(dispatcher: &D, buf: &[u8]) {
+    let captured = PENDING_EVENT.with(|cell| match cell.borrow_mut().as_mut() {
+        Some(pending) if pending.owner == owner_addr(dispatcher) => {
+            pending.buf.extend_from_slice(buf);
+            true
+        }
+        _ => false,
+    });
+
+    if !captured {
+        let mut message = queue::take_message_buffer();
+        message.push_str(&String::from_utf8_lossy(buf));
+        dispatcher.dispatch(LogEvent::new(message, Utc::now()));
+    }
+}
+
+fn owner_addr<D: ?Sized>(dispatcher: &D) -> usize {
+    (dispatcher as *const D).cast::<()>() as usize
 }
 
 pub struct NoopDispatcher {}
@@ -33,40 +262,47 @@ impl NoopDispatcher {
     }
 }
 
+/// Sends events to a running [`BatchExporter`](crate::BatchExporter), returned by
+/// [`BatchExporter::spawn`](crate::BatchExporter::spawn) and
+/// [`into_task`](crate::BatchExporter::into_task).
 pub struct CloudWatchDispatcher {
-    tx: UnboundedSender<LogEvent>,
+    tx: EventSender,
 }
 
 impl CloudWatchDispatcher {
-    pub(crate) fn new<C>(
-        client: C,
-        export_config: ExportConfig,
-        shutdown_rx: oneshot::Receiver<ShutdownSignal>,
-    ) -> Self
-    where
-        C: CloudWatchClient + Send + Sync + 'static,
-    {
-        // Should use bounded channel?
-        let (tx, rx) = mpsc::unbounded_channel();
-        let exporter = BatchExporter::new(client, export_config);
-
-        tokio::spawn(
-            exporter
-                .run(rx, shutdown_rx)
-                // Override the subscriber for the exporter to prevent recursively
-                // tracing new events from sdk calls within the exporter
-                .with_subscriber(tracing::dispatcher::Dispatch::none()),
-        );
-
+    pub(crate) fn new(tx: EventSender) -> Self {
         Self { tx }
     }
+
+    /// Send an event of any source to the exporter, e.g. a line of an access log.
+    ///
+    /// Never blocks: the event is dropped if the queue is full,
+    /// and discarded if the exporter is already shutting down.
+    pub fn send(&self, event: LogEvent) {
+        if self.in_own_exporter() {
+            return queue::recycle_message(event.message);
+        }
+        self.tx.send(event);
+    }
+
+    /// Whether this is called from the exporter of this dispatcher, e.g. through its diagnostics.
+    fn in_own_exporter(&self) -> bool {
+        EXPORTER_QUEUE
+            .try_with(|queue| *queue == self.tx.id())
+            .unwrap_or(false)
+    }
 }
 
 impl Dispatcher for CloudWatchDispatcher {
     fn dispatch(&self, event: LogEvent) {
-        // The exporter can already be shutting down when late logs arrive.
-        // Drop them instead of panicking the application.
-        let _ = self.tx.send(event);
+        self.send(event);
+    }
+
+    fn dispatch_deferred(&self, record: EventRecord, formatter: &Arc<dyn EventFormatter>) {
+        if self.in_own_exporter() {
+            return;
+        }
+        self.tx.send_deferred(record, formatter.clone());
     }
 }
 
@@ -82,11 +318,7 @@ impl std::io::Write for &NoopDispatcher {
 
 impl std::io::Write for &CloudWatchDispatcher {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        let timestamp = Utc::now();
-        let message = String::from_utf8_lossy(buf).to_string();
-
-        self.dispatch(LogEvent { message, timestamp });
-
+        write_event(*self, buf);
         Ok(buf.len())
     }
 
@@ -94,3 +326,227 @@ impl std::io::Write for &CloudWatchDispatcher {
         Ok(())
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tracing_core::{Kind, callsite::Callsite, field::FieldSet, identify_callsite};
+
+    struct TestCallsite;
+
+    impl Callsite for TestCallsite {
+        fn set_interest(&self, _: tracing_core::Interest) {}
+
+        fn metadata(&self) -> &Metadata<'_> {
+            unreachable!("test metadata is not registered")
+        }
+    }
+
+    static CALLSITE: TestCallsite = TestCallsite;
+
+    macro_rules! test_metadata {
+        ($($name:ident),*) => {$(
+            pub(crate) static $name: Metadata<'static> = Metadata::new(
+                "event",
+                "tracing_cloudwatch::test",
+                Level::$name,
+                Some("src/test.rs"),
+                Some(7),
+                Some("tracing_cloudwatch::test"),
+                FieldSet::new(&[], identify_callsite!(&CALLSITE)),
+                Kind::EVENT,
+            );
+        )*};
+    }
+
+    test_metadata!(TRACE, DEBUG, INFO, WARN, ERROR);
+
+    #[derive(Default)]
+    struct RecordingDispatcher {
+        events: Mutex<Vec<LogEvent>>,
+    }
+
+    impl Dispatcher for RecordingDispatcher {
+        fn dispatch(&self, event: LogEvent) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    #[test]
+    fn joins_writes_of_one_event() {
+        let dispatcher = RecordingDispatcher::default();
+        let timestamp = DateTime::from_timestamp_millis(1_000).unwrap();
+
+        let event = capture_event(&dispatcher, timestamp, || {
+            write_event(&dispatcher, b"{\"fields\":");
+            write_event(&dispatcher, b"{\"message\":\"hello\"}}");
+            write_event(&dispatcher, b"\n");
+        })
+        .unwrap();
+
+        assert_eq!(event.message, "{\"fields\":{\"message\":\"hello\"}}\n");
+        assert_eq!(event.timestamp, timestamp);
+        assert!(dispatcher.events.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn joins_multi_byte_characters_split_across_writes() {
+        let dispatcher = RecordingDispatcher::default();
+        let bytes = "é".as_bytes();
+
+        let event = capture_event(&dispatcher, Utc::now(), || {
+            write_event(&dispatcher, &bytes[..1]);
+            write_event(&dispatcher, &bytes[1..]);
+        })
+        .unwrap();
+
+        assert_eq!(event.message, "é");
+    }
+
+    #[test]
+    fn dispatches_writes_of_other_dispatchers_immediately() {
+        let dispatcher = RecordingDispatcher::default();
+        let other = RecordingDispatcher::default();
+
+        let event = capture_event(&dispatcher, Utc::now(), || {
+            write_event(&other, b"other");
+            capture_event(&other, Utc::now(), || write_event(&other, b"nested"));
+            write_event(&dispatcher, b"captured");
+        });
+
+        assert_eq!(event.unwrap().message, "captured");
+        assert_eq!(other.events.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn exposes_metadata_and_record() {
+        let event = LogEvent::new("plain", Utc::now());
+        assert_eq!(event.level(), None);
+        assert!(event.fields().is_empty() && event.spans().is_empty());
+
+        let event = event.with_metadata(&WARN);
+        assert_eq!(event.level(), Some(Level::WARN));
+        assert_eq!(event.target(), Some("tracing_cloudwatch::test"));
+        assert_eq!((event.file(), event.line()), (Some("src/test.rs"), Some(7)));
+
+        let record = EventRecord::new(Utc::now(), &WARN)
+            .with_field("user", FieldValue::Str("a".into()))
+            .with_span(SpanContext::new(3, "request").with_field("id", FieldValue::U64(1)));
+        let encoder = |record: &EventRecord, buf: &mut String| buf.push_str(record.target());
+        let event = LogEvent::encode(record, &encoder);
+        assert_eq!(event.message, "tracing_cloudwatch::test");
+        assert_eq!(event.level(), Some(Level::WARN));
+        assert_eq!(event.fields()[0].1, FieldValue::Str("a".into()));
+        assert_eq!(event.spans()[0].id(), 3);
+        assert_eq!(event.spans()[0].fields()[0].0, "id");
+
+        let part = event.with_message("part".to_string());
+        assert_eq!(part.target(), event.target());
+        assert_eq!(part.spans().len(), 1);
+    }
+
+    #[test]
+    fn captures_nothing_without_writes() {
+        let dispatcher = RecordingDispatcher::default();
+        assert!(capture_event(&dispatcher, Utc::now(), || {}).is_none());
+    }
+}
+
+#[cfg(test)]
+mod exporter_tests {
+    use super::*;
+    use crate::{
+        CloudWatchClient,
+        client::{BoxFuture, LogBatch, PutLogsError},
+        export::{BatchExporter, ExportConfig},
+    };
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[derive(Clone, Default)]
+    struct TracingClient {
+        messages: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl CloudWatchClient for TracingClient {
+        fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+            tracing::warn!("sdk retry");
+            let messages = batch.events.iter().map(|event| event.message.clone());
+            self.messages.lock().unwrap().extend(messages);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    async fn export(
+        config: ExportConfig,
+        f: impl FnOnce(&CloudWatchDispatcher) -> BoxFuture<'_, ()>,
+    ) -> Vec<String> {
+        let client = TracingClient::default();
+        let (dispatcher, guard, task) = BatchExporter::new(client.clone(), config).into_task();
+        let exporter = tokio::spawn(task);
+
+        f(&dispatcher).await;
+
+        guard.shutdown().await;
+        exporter.await.unwrap();
+        std::mem::take(&mut *client.messages.lock().unwrap())
+    }
+
+    fn event(message: &str) -> LogEvent {
+        LogEvent::new(message, Utc::now())
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn emits_exporter_events_to_diagnostics() {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let diagnostics = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .without_time()
+                .with_ansi(false)
+                .with_writer(move || writer.clone()),
+        );
+
+        export(
+            ExportConfig::default().with_diagnostics(diagnostics),
+            |dispatcher| Box::pin(async { dispatcher.dispatch(event("app")) }),
+        )
+        .await;
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("WARN"), "{output}");
+        assert!(output.contains("sdk retry"), "{output}");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn drops_events_of_its_own_exporter() {
+        let messages = export(ExportConfig::default(), |dispatcher| {
+            Box::pin(async {
+                EXPORTER_QUEUE
+                    .scope(dispatcher.tx.id(), async {
+                        dispatcher.dispatch(event("own"))
+                    })
+                    .await;
+                dispatcher.dispatch(event("app"));
+            })
+        })
+        .await;
+
+        assert_eq!(messages, ["app"]);
+    }
+}
@@ -1,27 +1,98 @@
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::sync::Arc;
 use std::time::Duration;
 
 use tokio::{
-    sync::{mpsc::UnboundedReceiver, oneshot},
-    time::interval,
+    sync::{oneshot, watch},
+    time::Instant,
+};
+use tracing::Level;
+
+mod circuit;
+mod error;
+mod preflight;
+mod supervisor;
+mod task;
+mod validate;
+
+pub use error::ExportError;
+pub use preflight::PreflightError;
+pub use task::ExporterTask;
+pub use validate::ConfigError;
+pub(crate) use validate::MAX_BATCH_SIZE;
+
+use circuit::Circuit;
+use error::ErrorHandler;
+
+use crate::{
+    CloudWatchClient,
+    batch::{self, BatchPolicy, FlushTrigger, PendingBatch, SharedBatchPolicy, ThresholdPolicy},
+    client::{LogBatch, NoopClient, middleware::Admission},
+    dead_letter::{DeadLetter, DeadLetterHandler, DeadLetterSink},
+    dispatch::LogEvent,
+    guard::{HealthState, ShutdownSignal},
+    oversized::{EVENT_OVERHEAD, OversizedEventHandler, OversizedEventPolicy},
+    queue::{self, EventReceiver},
+    resource::Resource,
+    shedding::LoadShedding,
 };
-
-use crate::{CloudWatchClient, client::NoopClient, dispatch::LogEvent, guard::ShutdownSignal};
 
 /// Configurations to control the behavior of exporting logs to CloudWatch.
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct ExportConfig {
     /// The number of logs to retain in the buffer within the interval period.
     batch_size: NonZeroUsize,
     /// The interval for putting logs.
+    #[cfg_attr(feature = "serde", serde(rename = "interval_ms", with = "duration_ms"))]
     interval: Duration,
     /// Where logs are sent.
     destination: LogDestination,
+    /// How to handle events exceeding the CloudWatch event size limit.
+    oversized_event_policy: OversizedEventPolicy,
+    /// The number of events waiting for the exporter before new events are dropped.
+    pub(crate) queue_capacity: NonZeroUsize,
+    /// Fields added to every event.
+    resource: BTreeMap<String, String>,
+    /// Consecutive failures opening the circuit breaker, 0 to disable it.
+    circuit_breaker_threshold: u32,
+    /// How long batches are dropped once the circuit breaker opened.
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "circuit_breaker_cooldown_ms", with = "duration_ms")
+    )]
+    circuit_breaker_cooldown: Duration,
+    /// Which events are dropped while the queue fills up.
+    pub(crate) load_shedding: Option<LoadShedding>,
+    /// Events at this level or more severe are exported immediately.
+    #[cfg_attr(feature = "serde", serde(with = "level_name"))]
+    flush_level: Option<Level>,
+    /// Fraction of the events less severe than WARN which are exported.
+    sample_rate: f64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    batch_policy: Option<SharedBatchPolicy>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    error_handler: ErrorHandler,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    dead_letter: Option<DeadLetterHandler>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) diagnostics: Option<tracing::Dispatch>,
 }
 
 /// Where logs are sent.
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct LogDestination {
     /// The name of the log group.
     pub log_group_name: String,
@@ -35,12 +106,26 @@ impl Default for ExportConfig {
             batch_size: NonZeroUsize::new(5).unwrap(),
             interval: Duration::from_secs(5),
             destination: LogDestination::default(),
+            oversized_event_policy: OversizedEventPolicy::default(),
+            queue_capacity: NonZeroUsize::new(10_000).unwrap(),
+            resource: BTreeMap::new(),
+            circuit_breaker_threshold: 0,
+            circuit_breaker_cooldown: Duration::from_secs(30),
+            flush_level: None,
+            sample_rate: 1.0,
+            load_shedding: None,
+            batch_policy: None,
+            error_handler: ErrorHandler::default(),
+            dead_letter: None,
+            diagnostics: None,
         }
     }
 }
 
 impl ExportConfig {
     /// Set batch size.
+    ///
+    /// Panics if it is zero, see [`Self::try_with_batch_size`].
     pub fn with_batch_size<T>(self, batch_size: T) -> Self
     where
         T: TryInto<NonZeroUsize>,
@@ -54,7 +139,7 @@ impl ExportConfig {
         }
     }
 
-    /// Set export interval.
+    /// Set how long an event may wait for its batch to fill up before it is exported.
     pub fn with_interval(self, interval: Duration) -> Self {
         Self { interval, ..self }
     }
@@ -80,12 +165,291 @@ impl ExportConfig {
             ..self
         }
     }
+
+    /// Set how events exceeding the CloudWatch event size limit (256 KB) are handled.
+    /// Default [`OversizedEventPolicy::Truncate`].
+    pub fn with_oversized_event_policy(self, oversized_event_policy: OversizedEventPolicy) -> Self {
+        Self {
+            oversized_event_policy,
+            ..self
+        }
+    }
+
+    /// Set how many events can wait for the exporter. Default 10000.
+    ///
+    /// Logging never blocks: events emitted while the queue is full are dropped and
+    /// reported by the exporter. The capacity is fixed when the layer is created,
+    /// so it is not changed by [`ReloadHandle`](crate::ReloadHandle).
+    ///
+    /// Panics if it is zero, see [`Self::try_with_queue_capacity`].
+    pub fn with_queue_capacity<T>(self, queue_capacity: T) -> Self
+    where
+        T: TryInto<NonZeroUsize>,
+        <T as TryInto<NonZeroUsize>>::Error: Debug,
+    {
+        Self {
+            queue_capacity: queue_capacity
+                .try_into()
+                .expect("queue capacity must be greater than or equal to 1"),
+            ..self
+        }
+    }
+
+    /// Drop TRACE, DEBUG and INFO events while the queue is above watermarks, keeping room for
+    /// WARN and ERROR events. Default none: events are only dropped once the queue is full.
+    ///
+    /// Like the queue capacity, this is fixed when the layer is created, so it is not changed by
+    /// [`ReloadHandle`](crate::ReloadHandle).
+    ///
+    /// ```rust
+    /// let config = tracing_cloudwatch::ExportConfig::default()
+    ///     .with_load_shedding(tracing_cloudwatch::LoadShedding::new().with_info_watermark(90));
+    /// # let _ = config;
+    /// ```
+    pub fn with_load_shedding(self, load_shedding: LoadShedding) -> Self {
+        Self {
+            load_shedding: Some(load_shedding),
+            ..self
+        }
+    }
+
+    /// Add a field to every exported event, e.g. the service name, version or environment.
+    ///
+    /// JSON messages get the field as a top-level key unless the event already has it,
+    /// other messages get ` key=value` appended.
+    pub fn with_resource_field(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.resource.insert(key.into(), value.into());
+        self
+    }
+
+    /// Add several fields to every exported event, e.g. detected with `ResourceDetector` of the
+    /// `resource_detector` feature. See [`Self::with_resource_field`].
+    pub fn with_resource<K, V>(mut self, resource: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.resource.extend(
+            resource
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into())),
+        );
+        self
+    }
+
+    /// Set when batches are exported, replacing the batch size and interval.
+    /// See [`ThresholdPolicy`](crate::ThresholdPolicy) and [`AdaptivePolicy`](crate::AdaptivePolicy).
+    ///
+    /// The policy is shared by the copies of this configuration, so the state of a policy such as
+    /// [`AdaptivePolicy`](crate::AdaptivePolicy) is kept when the configuration is reloaded.
+    pub fn with_batch_policy(self, batch_policy: impl BatchPolicy + 'static) -> Self {
+        Self {
+            batch_policy: Some(SharedBatchPolicy(Arc::new(batch_policy))),
+            ..self
+        }
+    }
+
+    /// The policy set with [`Self::with_batch_policy`], or one exporting batches of `batch_size`
+    /// events or 1 MB at least every `interval`.
+    fn batch_policy(&self) -> Arc<dyn BatchPolicy> {
+        match &self.batch_policy {
+            Some(SharedBatchPolicy(policy)) => policy.clone(),
+            None => Arc::new(
+                ThresholdPolicy::new()
+                    .with_max_events(self.batch_size.get())
+                    .with_max_bytes(batch::MAX_BATCH_BYTES)
+                    .with_max_age(self.interval),
+            ),
+        }
+    }
+
+    /// Export the pending batch as soon as an event at `level` or more severe is received,
+    /// e.g. [`Level::ERROR`], instead of waiting for the batch policy.
+    /// Default `None`: every event waits.
+    ///
+    /// The events waiting before it are sent in the same batch, so the order is kept.
+    /// Events already queued behind it are added to the batch, up to the batch size, so a burst
+    /// of errors is sent in a few requests rather than one request per event.
+    /// Only applies to tracing events, not to span lifecycle records.
+    pub fn with_flush_level(self, level: impl Into<Option<Level>>) -> Self {
+        Self {
+            flush_level: level.into(),
+            ..self
+        }
+    }
+
+    /// Export only a fraction of the INFO, DEBUG and TRACE events, and of span lifecycle records,
+    /// between 0.0 and 1.0. WARN and ERROR events are always exported. Default 1.0.
+    /// Rates outside of this range are clamped to it, and NaN exports every event.
+    ///
+    /// Kept events are evenly spread: with a rate of 0.25, one event in four is exported.
+    /// Can be changed on a running exporter with the [`ReloadHandle`](crate::ReloadHandle),
+    /// e.g. to export every event during an incident.
+    pub fn with_sample_rate(self, sample_rate: f64) -> Self {
+        Self {
+            sample_rate: if sample_rate.is_nan() {
+                1.0
+            } else {
+                sample_rate.clamp(0.0, 1.0)
+            },
+            ..self
+        }
+    }
+
+    /// Stop calling the client after `failure_threshold` consecutive failed batches, e.g. while
+    /// CloudWatch is unavailable or credentials have expired. Disabled by default.
+    ///
+    /// While the circuit is open, batches are dropped without calling the client. After `cooldown`,
+    /// the next batch is sent as a probe: the circuit closes if it succeeds and opens again otherwise.
+    /// A `failure_threshold` of 0 disables the circuit breaker.
+    pub fn with_circuit_breaker(self, failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            circuit_breaker_threshold: failure_threshold,
+            circuit_breaker_cooldown: cooldown,
+            ..self
+        }
+    }
+
+    /// Set the handler receiving failed batches, dropped events and circuit breaker state changes.
+    /// Default prints them to stderr.
+    ///
+    /// The handler runs on the exporter task, so it should not block.
+    /// Events it emits through `tracing` are not exported.
+    pub fn with_error_handler(
+        self,
+        error_handler: impl Fn(&ExportError) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            error_handler: ErrorHandler::new(error_handler),
+            ..self
+        }
+    }
+
+    /// Set the sink receiving the events of batches which could not be exported, with the error.
+    ///
+    /// A batch is dead-lettered when the client returns an error, i.e. after the retries of the
+    /// [`Retry`](crate::middleware::Retry) layer if any, or when it is dropped while the circuit
+    /// breaker is open. See [`JsonlDeadLetter`](crate::JsonlDeadLetter) to keep them in a file.
+    pub fn with_dead_letter(self, dead_letter: impl DeadLetterSink + 'static) -> Self {
+        Self {
+            dead_letter: Some(DeadLetterHandler::new(dead_letter)),
+            ..self
+        }
+    }
+
+    /// Set the subscriber receiving the events emitted by the exporter task, such as retries,
+    /// credential refreshes and connection errors of the SDK, and the [`ExportError`]s of the
+    /// default error handler. Default none: events are discarded and errors printed to stderr.
+    ///
+    /// Events of the exporter are never exported by the layer owning it, even if `diagnostics`
+    /// contains that layer. Another [`CloudWatchLayer`](crate::CloudWatchLayer) can export them
+    /// to a dedicated log stream, as long as its own diagnostics do not lead back to this one.
+    /// The subscriber is fixed when the layer is created, so it is not changed by
+    /// [`ReloadHandle`](crate::ReloadHandle).
+    ///
+    /// ```rust
+    /// use tracing_subscriber::{Layer, filter::LevelFilter, layer::SubscriberExt};
+    ///
+    /// let diagnostics = tracing_subscriber::registry().with(
+    ///     tracing_subscriber::fmt::layer()
+    ///         .with_writer(std::io::stderr)
+    ///         .with_filter(LevelFilter::WARN),
+    /// );
+    /// let config = tracing_cloudwatch::ExportConfig::default().with_diagnostics(diagnostics);
+    /// # let _ = config;
+    /// ```
+    pub fn with_diagnostics(self, diagnostics: impl Into<tracing::Dispatch>) -> Self {
+        Self {
+            diagnostics: Some(diagnostics.into()),
+            ..self
+        }
+    }
+}
+
+/// (De)serialize a [`Duration`] as whole milliseconds.
+#[cfg(feature = "serde")]
+mod duration_ms {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        value: &Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(value.as_millis().try_into().unwrap_or(u64::MAX))
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+/// (De)serialize an optional [`Level`] as its name, e.g. `"error"`.
+#[cfg(feature = "serde")]
+mod level_name {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+    use tracing::Level;
+
+    pub(super) fn serialize<S: Serializer>(
+        value: &Option<Level>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(level) => serializer.serialize_str(&level.as_str().to_ascii_lowercase()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Level>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|name| name.parse().map_err(D::Error::custom))
+            .transpose()
+    }
 }
 
-pub(crate) struct BatchExporter<C> {
+/// Exports batches of [`LogEvent`]s with a [`CloudWatchClient`], the worker behind
+/// [`CloudWatchLayer`](crate::CloudWatchLayer).
+///
+/// It can also ship logs of other sources: events sent with [`CloudWatchDispatcher::send`](crate::CloudWatchDispatcher::send)
+/// are batched, retried and dead-lettered like tracing events.
+///
+/// ```rust,no_run
+/// # async fn run(client: impl tracing_cloudwatch::CloudWatchClient + 'static) {
+/// use tracing_cloudwatch::{BatchExporter, ExportConfig, LogEvent};
+///
+/// let config = ExportConfig::default()
+///     .with_log_group_name("access-logs")
+///     .with_log_stream_name("stream-1");
+/// let (dispatcher, guard) = BatchExporter::new(client, config).spawn();
+/// dispatcher.send(LogEvent::new("GET /index.html 200", chrono::Utc::now()));
+/// guard.shutdown().await;
+/// # }
+/// ```
+pub struct BatchExporter<C> {
     client: C,
     queue: Vec<LogEvent>,
+    /// Size of the events in `queue`, see [`PendingBatch::bytes`].
+    queued_bytes: usize,
+    /// When the oldest event in `queue` was received.
+    oldest_received: Option<Instant>,
+    /// Fraction of an event earned by [`ExportConfig::with_sample_rate`], an event is kept at 1.
+    sample_credit: f64,
+    policy: Arc<dyn BatchPolicy>,
+    oversized: OversizedEventHandler,
+    /// [`ExportConfig::with_resource`] fields, escaped once for every event.
+    resource: Resource,
     config: ExportConfig,
+    reload_rx: Option<watch::Receiver<ExportConfig>>,
+    next_batch_id: u64,
+    circuit: Circuit,
+    health: HealthState,
+    shutting_down: bool,
+    shutdown_signal: Option<ShutdownSignal>,
 }
 
 impl Default for BatchExporter<NoopClient> {
@@ -95,90 +459,304 @@ impl Default for BatchExporter<NoopClient> {
 }
 
 impl<C> BatchExporter<C> {
-    pub(crate) fn new(client: C, config: ExportConfig) -> Self {
+    /// An exporter sending the events it receives with `client`, configured by `config`.
+    ///
+    /// Nothing runs until it is started with [`spawn`](Self::spawn), or with
+    /// [`into_task`](Self::into_task) on another runtime. `config` is used as is, check it first
+    /// with [`ExportConfig::try_build`] or [`ExportConfig::validate`].
+    pub fn new(client: C, config: ExportConfig) -> Self {
         Self {
             client,
+            oversized: OversizedEventHandler::new(config.oversized_event_policy),
+            policy: config.batch_policy(),
+            resource: Resource::new(&config.resource),
             config,
             queue: Vec::new(),
+            queued_bytes: 0,
+            oldest_received: None,
+            sample_credit: 0.0,
+            reload_rx: None,
+            next_batch_id: 0,
+            circuit: Circuit::default(),
+            health: HealthState::default(),
+            shutting_down: false,
+            shutdown_signal: None,
+        }
+    }
+
+    /// Apply configurations sent through the [`ReloadHandle`](crate::ReloadHandle) while running.
+    pub(crate) fn with_reload(self, reload_rx: watch::Receiver<ExportConfig>) -> Self {
+        Self {
+            reload_rx: Some(reload_rx),
+            ..self
         }
     }
+
+    /// Publish the state of the worker, e.g. to [`CloudWatchWorkerGuard::health`](crate::CloudWatchWorkerGuard::health).
+    pub(crate) fn with_health(self, health: HealthState) -> Self {
+        Self { health, ..self }
+    }
 }
 
 impl<C> BatchExporter<C>
 where
     C: CloudWatchClient + Send + Sync + 'static,
 {
-    pub(crate) async fn run(
-        mut self,
-        mut rx: UnboundedReceiver<LogEvent>,
-        mut shutdown_rx: oneshot::Receiver<ShutdownSignal>,
+    /// Export events until shutdown. Returns early only by panicking, in which case the events
+    /// already in `self.queue` are kept and the loop can be run again by the supervisor.
+    async fn run_loop(
+        &mut self,
+        rx: &mut EventReceiver,
+        shutdown_rx: &mut oneshot::Receiver<ShutdownSignal>,
+        reload_rx: &mut Option<watch::Receiver<ExportConfig>>,
     ) {
-        let mut interval = interval(self.config.interval);
-        let mut shutdown_signal = None;
-
-        loop {
-            tokio::select! {
-                 _ = interval.tick() => {
-                    if self.queue.is_empty() {
-                        continue;
-                    }
-                }
+        while !self.shutting_down {
+            let trigger = tokio::select! {
+                _ = Self::expired(self.deadline()) => FlushTrigger::MaxAge,
 
                 event = rx.recv() => {
                     let Some(event) = event else {
                         break;
                     };
 
-                    self.queue.push(event);
-                    if self.queue.len() < <NonZeroUsize as Into<usize>>::into(self.config.batch_size) {
-                        continue
+                    if self.is_urgent(&event) {
+                        self.push(event);
+                        // Send the events queued behind it along, so bursts share requests.
+                        while !self.is_full()
+                            && let Some(event) = rx.try_recv()
+                        {
+                            self.push(event);
+                        }
+                        FlushTrigger::FlushLevel
+                    } else {
+                        self.push(event);
+                        if !self.is_full() {
+                            continue;
+                        }
+                        FlushTrigger::Full
                     }
                 }
 
-                received_shutdown = &mut shutdown_rx => {
-                    if let Ok(signal) = received_shutdown {
-                        shutdown_signal = Some(signal);
-                    }
-                    while let Ok(event) = rx.try_recv() {
-                        self.queue.push(event);
+                config = Self::reloaded(reload_rx) => {
+                    // Flush logs emitted before the reload with the previous configuration.
+                    while let Some(event) = rx.try_recv() {
+                        self.push(event);
                     }
-                    break;
+                    self.flush(FlushTrigger::Reload).await;
+                    self.oversized.set_policy(config.oversized_event_policy);
+                    self.policy = config.batch_policy();
+                    self.resource = Resource::new(&config.resource);
+                    self.config = config;
+                    continue;
                 }
+
+                received_shutdown = &mut *shutdown_rx => {
+                    self.shutting_down = true;
+                    self.shutdown_signal = received_shutdown.ok();
+                    FlushTrigger::Shutdown
+                }
+            };
+            self.report_dropped(rx);
+            self.flush(trigger).await;
+        }
+        while let Some(event) = rx.try_recv() {
+            self.push(event);
+        }
+        self.report_dropped(rx);
+        self.flush(FlushTrigger::Shutdown).await;
+    }
+
+    /// When the pending batch has to be exported according to the policy, if it is not empty.
+    fn deadline(&self) -> Option<Instant> {
+        let max_age = self.policy.max_age()?;
+        self.oldest_received
+            .and_then(|oldest| oldest.checked_add(max_age))
+    }
+
+    /// Wait until `deadline`, or forever without one, so an idle exporter is never woken up.
+    async fn expired(deadline: Option<Instant>) {
+        match deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn pending(&self) -> PendingBatch {
+        PendingBatch {
+            events: self.queue.len(),
+            bytes: self.queued_bytes,
+            age: self
+                .oldest_received
+                .map(|oldest| oldest.elapsed())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.policy.is_full(&self.pending())
+    }
+
+    /// Wait for the next configuration sent through the reload handle.
+    /// Never resolves if reloading is disabled or every handle has been dropped.
+    async fn reloaded(reload_rx: &mut Option<watch::Receiver<ExportConfig>>) -> ExportConfig {
+        if let Some(rx) = reload_rx {
+            if rx.changed().await.is_ok() {
+                return rx.borrow_and_update().clone();
             }
-            self.flush().await;
+            *reload_rx = None;
+        }
+        std::future::pending().await
+    }
+
+    /// Whether the event is at the flush level or more severe.
+    fn is_urgent(&self, event: &LogEvent) -> bool {
+        match (event.level(), self.config.flush_level) {
+            (Some(level), Some(flush_level)) => level <= flush_level,
+            _ => false,
+        }
+    }
+
+    /// Whether the event is left out by [`ExportConfig::with_sample_rate`].
+    fn sampled_out(&mut self, event: &LogEvent) -> bool {
+        let sample_rate = self.config.sample_rate;
+        if sample_rate >= 1.0 || event.level().is_some_and(|level| level <= Level::WARN) {
+            return false;
+        }
+        self.sample_credit += sample_rate;
+        if self.sample_credit < 1.0 {
+            return true;
+        }
+        self.sample_credit -= 1.0;
+        false
+    }
+
+    fn push(&mut self, mut event: LogEvent) {
+        if self.sampled_out(&event) {
+            return queue::recycle_message(event.message);
+        }
+        self.resource.enrich(&mut event.message);
+        let queued = self.queue.len();
+        let dropped = self.oversized.push(event, &mut self.queue);
+        self.queued_bytes += self.queue[queued..]
+            .iter()
+            .map(|event| event.message.len() + EVENT_OVERHEAD)
+            .sum::<usize>();
+        if queued == 0 && !self.queue.is_empty() {
+            self.oldest_received = Some(Instant::now());
         }
-        self.flush().await;
-        if let Some(shutdown_signal) = shutdown_signal {
-            shutdown_signal.ack();
+        if let Some(bytes) = dropped {
+            self.config
+                .error_handler
+                .report(ExportError::OversizedEventDropped {
+                    bytes,
+                    total: self.oversized.dropped(),
+                });
         }
     }
 
-    async fn flush(&mut self) {
-        let logs: Vec<LogEvent> = Self::take_from_queue(&mut self.queue);
+    fn report_dropped(&self, rx: &EventReceiver) {
+        let [trace, debug, info] = rx.take_shed();
+        if trace + debug + info > 0 {
+            self.config
+                .error_handler
+                .report(ExportError::EventsShed { trace, debug, info });
+        }
+        let count = rx.take_dropped();
+        if count > 0 {
+            self.config
+                .error_handler
+                .report(ExportError::QueueFull { count });
+        }
+    }
 
-        if logs.is_empty() {
+    async fn flush(&mut self, trigger: FlushTrigger) {
+        if self.queue.is_empty() {
             return;
         }
+        let pending = self.pending();
+        Self::sort_queue(&mut self.queue);
+
+        // Send the queue in as many requests as the `PutLogEvents` limits require.
+        let mut sent = 0;
+        while sent < self.queue.len() {
+            let len = batch::batch_len(&self.queue[sent..]);
+            self.send(sent..sent + len).await;
+            sent += len;
+        }
+
+        self.policy.on_flush(&pending, trigger);
+        self.clear_queue();
+    }
+
+    /// Send the events of `range` in the queue as one batch.
+    async fn send(&mut self, range: Range<usize>) {
+        let cooldown = self.config.circuit_breaker_cooldown;
+        match self.circuit.admit(cooldown) {
+            Admission::Send => {}
+            Admission::Probe => self
+                .config
+                .error_handler
+                .report(ExportError::CircuitHalfOpen),
+            Admission::Reject => {
+                self.circuit.reject(range.len());
+                self.dead_letter(&self.queue[range], &ExportError::CircuitOpen);
+                return;
+            }
+        }
 
-        if let Err(err) = self
-            .client
-            .put_logs(self.config.destination.clone(), logs)
+        let events = &self.queue[range];
+        let batch = LogBatch::new(&self.config.destination, events, self.next_batch_id);
+        self.next_batch_id += 1;
+
+        let result = supervisor::catch_unwind(async { self.client.put_logs(batch).await })
             .await
-        {
-            eprintln!(
-                "[tracing-cloudwatch] Unable to put logs to cloudwatch. Error: {err:?} {:?}",
-                self.config.destination
-            );
+            .unwrap_or_else(|panic| {
+                Err(
+                    anyhow::anyhow!("put_logs panicked: {}", supervisor::panic_message(&*panic))
+                        .into(),
+                )
+            });
+        let state_change = self.circuit.record(
+            result.is_ok(),
+            self.config.circuit_breaker_threshold,
+            cooldown,
+        );
+        if let Err(error) = result {
+            let error = ExportError::PutLogs {
+                error,
+                destination: self.config.destination.clone(),
+                events: events.len(),
+            };
+            self.dead_letter(events, &error);
+            self.config.error_handler.report(error);
+        }
+        if let Some(state_change) = state_change {
+            self.config.error_handler.report(state_change);
+        }
+    }
+
+    fn dead_letter(&self, events: &[LogEvent], error: &ExportError) {
+        if let Some(dead_letter) = &self.config.dead_letter {
+            dead_letter.send(DeadLetter {
+                destination: &self.config.destination,
+                events,
+                error,
+            });
+        }
+    }
+
+    fn clear_queue(&mut self) {
+        self.queued_bytes = 0;
+        self.oldest_received = None;
+        // Keep the batch buffer and the message allocations for the next events.
+        for event in self.queue.drain(..) {
+            queue::recycle_message(event.message);
         }
     }
 
-    fn take_from_queue(queue: &mut Vec<LogEvent>) -> Vec<LogEvent> {
+    fn sort_queue(queue: &mut [LogEvent]) {
         if cfg!(feature = "ordered_logs") {
-            let mut logs = std::mem::take(queue);
-            logs.sort_by_key(|log| log.timestamp);
-            logs
-        } else {
-            std::mem::take(queue)
+            queue.sort_by_key(|log| log.timestamp);
         }
     }
 }
@@ -189,28 +767,37 @@ mod tests {
 
     mod helper {
         use super::*;
-        use async_trait::async_trait;
+        use crate::client::{BoxFuture, PutLogsError};
         use std::sync::{Arc, Mutex};
         use tokio::time::{sleep, timeout};
 
         #[derive(Clone, Default)]
         pub(super) struct RecordingClient {
             logs: Arc<Mutex<Vec<LogEvent>>>,
+            streams: Arc<Mutex<Vec<String>>>,
         }
 
-        #[async_trait]
         impl CloudWatchClient for RecordingClient {
-            async fn put_logs(
-                &self,
-                _dest: LogDestination,
-                logs: Vec<LogEvent>,
-            ) -> Result<(), crate::client::PutLogsError> {
-                self.logs.lock().unwrap().extend(logs);
-                Ok(())
+            fn put_logs<'a>(
+                &'a self,
+                batch: LogBatch<'a>,
+            ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+                let mut streams = self.streams.lock().unwrap();
+                streams.extend(std::iter::repeat_n(
+                    batch.destination.log_stream_name.clone(),
+                    batch.events.len(),
+                ));
+                self.logs.lock().unwrap().extend_from_slice(batch.events);
+                Box::pin(async { Ok(()) })
             }
         }
 
         impl RecordingClient {
+            /// Log stream name each exported event was sent to.
+            pub(super) fn exported_streams(&self) -> Vec<String> {
+                self.streams.lock().unwrap().clone()
+            }
+
             pub(super) fn exported_count(&self) -> usize {
                 self.logs.lock().unwrap().len()
             }
@@ -225,6 +812,30 @@ mod tests {
             }
         }
 
+        /// Records the number of events and bytes of each request.
+        #[derive(Clone, Default)]
+        pub(super) struct SizeRecordingClient {
+            pub(super) requests: Arc<Mutex<Vec<(usize, usize)>>>,
+        }
+
+        impl CloudWatchClient for SizeRecordingClient {
+            fn put_logs<'a>(
+                &'a self,
+                batch: LogBatch<'a>,
+            ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+                let bytes = batch
+                    .events
+                    .iter()
+                    .map(|event| event.message.len() + EVENT_OVERHEAD)
+                    .sum();
+                self.requests
+                    .lock()
+                    .unwrap()
+                    .push((batch.events.len(), bytes));
+                Box::pin(async { Ok(()) })
+            }
+        }
+
         pub(super) async fn wait_for_exported_count(client: &RecordingClient, expected: usize) {
             timeout(Duration::from_secs(1), async {
                 loop {
@@ -239,6 +850,7 @@ mod tests {
         }
     }
 
+    #[allow(clippy::identity_op)]
     mod ordering {
         use super::*;
         use chrono::{DateTime, Utc};
@@ -250,18 +862,9 @@ mod tests {
 
         fn unordered_queue() -> Vec<LogEvent> {
             vec![
-                LogEvent {
-                    message: "1".to_string(),
-                    timestamp: DAY_ONE,
-                },
-                LogEvent {
-                    message: "3".to_string(),
-                    timestamp: DAY_THREE,
-                },
-                LogEvent {
-                    message: "2".to_string(),
-                    timestamp: DAY_TWO,
-                },
+                LogEvent::new("1", DAY_ONE),
+                LogEvent::new("3", DAY_THREE),
+                LogEvent::new("2", DAY_TWO),
             ]
         }
 
@@ -283,9 +886,8 @@ mod tests {
         #[cfg(not(feature = "ordered_logs"))]
         #[test]
         fn does_not_order_logs_by_default() {
-            let mut unordered_queue = unordered_queue();
-            let still_unordered_queue =
-                BatchExporter::<NoopClient>::take_from_queue(&mut unordered_queue);
+            let mut still_unordered_queue = unordered_queue();
+            BatchExporter::<NoopClient>::sort_queue(&mut still_unordered_queue);
 
             let mut still_unordered_queue_iter = still_unordered_queue.iter();
             assert_eq!(
@@ -305,19 +907,349 @@ mod tests {
         #[cfg(feature = "ordered_logs")]
         #[test]
         fn orders_logs_when_enabled() {
-            let mut unordered_queue = unordered_queue();
-            let ordered_queue = BatchExporter::<NoopClient>::take_from_queue(&mut unordered_queue);
+            let mut ordered_queue = unordered_queue();
+            BatchExporter::<NoopClient>::sort_queue(&mut ordered_queue);
             assert_is_ordered(ordered_queue);
         }
     }
 
+    mod circuit_breaker {
+        use super::*;
+        use crate::client::{BoxFuture, PutLogsError};
+        use chrono::Utc;
+        use std::sync::{
+            Arc, Mutex,
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+        };
+
+        #[derive(Clone, Default)]
+        struct FlakyClient {
+            calls: Arc<AtomicUsize>,
+            healthy: Arc<AtomicBool>,
+        }
+
+        impl CloudWatchClient for FlakyClient {
+            fn put_logs<'a>(&'a self, _: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+                self.calls.fetch_add(1, Ordering::Relaxed);
+                let healthy = self.healthy.load(Ordering::Relaxed);
+                Box::pin(async move {
+                    if healthy {
+                        Ok(())
+                    } else {
+                        Err(anyhow::anyhow!("unavailable").into())
+                    }
+                })
+            }
+        }
+
+        async fn flush_one(exporter: &mut BatchExporter<FlakyClient>) {
+            exporter.push(LogEvent::new("event", Utc::now()));
+            exporter.flush(FlushTrigger::Full).await;
+        }
+
+        #[tokio::test(flavor = "current_thread", start_paused = true)]
+        async fn opens_after_consecutive_failures_and_closes_after_probe() {
+            let client = FlakyClient::default();
+            let reported = Arc::new(Mutex::new(Vec::new()));
+            let recorded = reported.clone();
+            let dead_letters = Arc::new(AtomicUsize::new(0));
+            let dead_lettered = dead_letters.clone();
+            let mut exporter = BatchExporter::new(
+                client.clone(),
+                ExportConfig::default()
+                    .with_circuit_breaker(2, Duration::from_secs(10))
+                    .with_error_handler(move |error| {
+                        recorded.lock().unwrap().push(error.to_string())
+                    })
+                    .with_dead_letter(move |dead_letter: crate::DeadLetter<'_>| {
+                        dead_lettered.fetch_add(dead_letter.events.len(), Ordering::Relaxed);
+                    }),
+            );
+
+            for _ in 0..4 {
+                flush_one(&mut exporter).await;
+            }
+            // Open after two failures, the next batches are dropped.
+            assert_eq!(client.calls.load(Ordering::Relaxed), 2);
+            assert_eq!(dead_letters.load(Ordering::Relaxed), 4);
+
+            tokio::time::advance(Duration::from_secs(10)).await;
+            client.healthy.store(true, Ordering::Relaxed);
+            flush_one(&mut exporter).await;
+            flush_one(&mut exporter).await;
+            assert_eq!(client.calls.load(Ordering::Relaxed), 4);
+
+            let reported = reported.lock().unwrap();
+            assert!(reported[0].starts_with("Unable to put logs"));
+            assert_eq!(
+                reported[2..],
+                [
+                    "Circuit breaker opened after 2 consecutive failures, dropping batches for 10s",
+                    "Circuit breaker half-open, sending a probe batch",
+                    "Circuit breaker closed, 2 events were dropped while it was open",
+                ]
+            );
+        }
+    }
+
+    mod supervisor {
+        use super::helper::RecordingClient;
+        use super::*;
+        use crate::{
+            client::{BoxFuture, PutLogsError},
+            guard::WorkerHealth,
+        };
+        use chrono::Utc;
+        use std::sync::{
+            Arc, Mutex,
+            atomic::{AtomicUsize, Ordering},
+        };
+
+        /// Client failing, or panicking, on its first calls.
+        struct FailingClient {
+            calls: AtomicUsize,
+            failures: usize,
+            panic: bool,
+            inner: RecordingClient,
+        }
+
+        impl FailingClient {
+            fn new(failures: usize, panic: bool) -> Self {
+                Self {
+                    calls: AtomicUsize::new(0),
+                    failures,
+                    panic,
+                    inner: RecordingClient::default(),
+                }
+            }
+        }
+
+        impl CloudWatchClient for FailingClient {
+            fn put_logs<'a>(
+                &'a self,
+                batch: LogBatch<'a>,
+            ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+                if self.calls.fetch_add(1, Ordering::Relaxed) >= self.failures {
+                    return self.inner.put_logs(batch);
+                }
+                if self.panic {
+                    panic!("client bug");
+                }
+                Box::pin(async { Err(anyhow::anyhow!("unavailable").into()) })
+            }
+        }
+
+        async fn run_one_event(
+            client: FailingClient,
+            config: ExportConfig,
+        ) -> (Arc<FailingClient>, HealthState) {
+            let client = Arc::new(client);
+            let health = HealthState::default();
+            let exporter = BatchExporter::new(client.clone(), config).with_health(health.clone());
+            let (tx, rx) = queue::event_queue(16, None);
+            let (shutdown_tx, shutdown_rx) = oneshot::channel();
+            let (shutdown_signal, ack_rx) = ShutdownSignal::new();
+
+            tx.send(LogEvent::new("event", Utc::now()));
+            shutdown_tx.send(shutdown_signal).unwrap();
+            exporter.run(rx, shutdown_rx).await;
+            ack_rx.await.expect("shutdown acknowledged");
+
+            (client, health)
+        }
+
+        #[tokio::test(flavor = "current_thread")]
+        async fn reports_client_panics_as_failed_batches() {
+            let reported = Arc::new(Mutex::new(Vec::new()));
+            let recorded = reported.clone();
+            let config = ExportConfig::default()
+                .with_error_handler(move |error| recorded.lock().unwrap().push(error.to_string()));
+
+            let (_, health) = run_one_event(FailingClient::new(1, true), config).await;
+
+            assert_eq!(health.get(), WorkerHealth::Stopped);
+            let reported = reported.lock().unwrap();
+            assert!(
+                reported[0].contains("put_logs panicked: client bug"),
+                "{reported:?}"
+            );
+        }
+
+        #[tokio::test(flavor = "current_thread", start_paused = true)]
+        async fn restarts_with_queued_events() {
+            let reported = Arc::new(Mutex::new(Vec::new()));
+            let recorded = reported.clone();
+            let panicked = AtomicUsize::new(0);
+            let config = ExportConfig::default()
+                .with_error_handler(move |error| recorded.lock().unwrap().push(error.to_string()))
+                .with_dead_letter(move |_: crate::DeadLetter<'_>| {
+                    if panicked.fetch_add(1, Ordering::Relaxed) == 0 {
+                        panic!("sink bug");
+                    }
+                });
+
+            let (client, health) = run_one_event(FailingClient::new(1, false), config).await;
+
+            // The event kept across the restart is sent again.
+            assert_eq!(client.inner.exported_messages(), ["event"]);
+            assert_eq!(health.get(), WorkerHealth::Stopped);
+            let reported = reported.lock().unwrap();
+            assert_eq!(
+                reported.last().unwrap(),
+                "Exporter panicked: sink bug. Restarting (restart 1)"
+            );
+        }
+    }
+
+    mod batch_policy {
+        use super::helper::{RecordingClient, SizeRecordingClient};
+        use super::*;
+        use chrono::Utc;
+        use std::sync::{
+            Mutex,
+            atomic::{AtomicUsize, Ordering},
+        };
+
+        /// Flushes at 100 bytes or after a second, recording how often the exporter asked.
+        #[derive(Default)]
+        struct RecordingPolicy {
+            deadline_checks: AtomicUsize,
+            flushes: Mutex<Vec<(PendingBatch, FlushTrigger)>>,
+        }
+
+        impl BatchPolicy for RecordingPolicy {
+            fn is_full(&self, batch: &PendingBatch) -> bool {
+                batch.bytes() >= 100
+            }
+
+            fn max_age(&self) -> Option<Duration> {
+                self.deadline_checks.fetch_add(1, Ordering::Relaxed);
+                Some(Duration::from_secs(1))
+            }
+
+            fn on_flush(&self, batch: &PendingBatch, trigger: FlushTrigger) {
+                self.flushes.lock().unwrap().push((*batch, trigger));
+            }
+        }
+
+        fn event(message: &str) -> LogEvent {
+            LogEvent::new(message, Utc::now())
+        }
+
+        #[tokio::test(flavor = "current_thread", start_paused = true)]
+        async fn flushes_by_bytes_and_age_without_idle_wakeups() {
+            let client = RecordingClient::default();
+            let policy = Arc::new(RecordingPolicy::default());
+            let exporter = BatchExporter::new(
+                client.clone(),
+                ExportConfig::default()
+                    .with_batch_policy(policy.clone())
+                    .with_log_group_name("group")
+                    .with_log_stream_name("stream"),
+            );
+            let (tx, rx) = queue::event_queue(16, None);
+            let (shutdown_tx, shutdown_rx) = oneshot::channel();
+            let worker = tokio::spawn(exporter.run(rx, shutdown_rx));
+
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+            assert_eq!(policy.deadline_checks.load(Ordering::Relaxed), 1);
+
+            // 26 bytes of overhead per event: the second event reaches 100 bytes.
+            tx.send(event(&"a".repeat(24)));
+            tx.send(event(&"b".repeat(24)));
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            assert_eq!(client.exported_count(), 2);
+
+            tx.send(event("late"));
+            tokio::time::sleep(Duration::from_millis(999)).await;
+            assert_eq!(client.exported_count(), 2);
+            tokio::time::sleep(Duration::from_millis(2)).await;
+            assert_eq!(client.exported_count(), 3);
+
+            let (shutdown_signal, _ack_rx) = ShutdownSignal::new();
+            shutdown_tx.send(shutdown_signal).unwrap();
+            worker.await.unwrap();
+
+            let flushes = policy.flushes.lock().unwrap();
+            assert_eq!(
+                flushes
+                    .iter()
+                    .map(|(batch, trigger)| (batch.events(), batch.bytes(), *trigger))
+                    .collect::<Vec<_>>(),
+                [(2, 100, FlushTrigger::Full), (1, 30, FlushTrigger::MaxAge)]
+            );
+        }
+
+        #[tokio::test(flavor = "current_thread")]
+        async fn splits_flushes_within_request_limits() {
+            let client = SizeRecordingClient::default();
+            let exporter = BatchExporter::new(
+                client.clone(),
+                ExportConfig::default()
+                    .with_batch_policy(ThresholdPolicy::new())
+                    .with_log_group_name("group")
+                    .with_log_stream_name("stream"),
+            );
+            let (tx, rx) = queue::event_queue(20_000, None);
+            let large = "x".repeat(200 * 1024);
+            for _ in 0..6 {
+                tx.send(event(&large));
+            }
+            for _ in 0..12_000 {
+                tx.send(event("small"));
+            }
+
+            // Everything is sent by the flush on shutdown.
+            let (shutdown_tx, shutdown_rx) = oneshot::channel();
+            let (shutdown_signal, _ack_rx) = ShutdownSignal::new();
+            shutdown_tx.send(shutdown_signal).unwrap();
+            exporter.run(rx, shutdown_rx).await;
+
+            let requests = client.requests.lock().unwrap();
+            assert!(requests.len() > 2, "{requests:?}");
+            for &(events, bytes) in requests.iter() {
+                assert!(events <= MAX_BATCH_SIZE, "{events} events");
+                assert!(bytes <= batch::MAX_BATCH_BYTES, "{bytes} bytes");
+            }
+            let events: usize = requests.iter().map(|&(events, _)| events).sum();
+            assert_eq!(events, 12_006);
+        }
+    }
+
     mod integration {
         use super::helper::{RecordingClient, wait_for_exported_count};
         use super::*;
         use chrono::Utc;
-        use tokio::time::sleep;
         use tracing_subscriber::layer::SubscriberExt;
 
+        #[tokio::test(flavor = "current_thread")]
+        async fn samples_events_less_severe_than_warn() {
+            use crate::dispatch::tests::{INFO, WARN};
+
+            let client = RecordingClient::default();
+            let (dispatcher, guard) = BatchExporter::new(
+                client.clone(),
+                ExportConfig::default()
+                    .with_sample_rate(0.5)
+                    .with_log_group_name("group")
+                    .with_log_stream_name("stream"),
+            )
+            .spawn();
+
+            for (message, metadata) in [
+                ("info-1", &INFO),
+                ("info-2", &INFO),
+                ("warn", &WARN),
+                ("info-3", &INFO),
+                ("info-4", &INFO),
+            ] {
+                dispatcher.send(LogEvent::new(message, Utc::now()).with_metadata(metadata));
+            }
+            guard.shutdown().await;
+
+            assert_eq!(client.exported_messages(), ["info-2", "warn", "info-4"]);
+        }
+
         #[tokio::test(flavor = "current_thread")]
         async fn drains_all_buffered_events_on_shutdown() {
             let client = RecordingClient::default();
@@ -330,17 +1262,13 @@ mod tests {
                     .with_log_stream_name("stream"),
             );
 
-            let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+            let (tx, rx) = queue::event_queue(1024, None);
             let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<ShutdownSignal>();
             let (shutdown_signal, _ack_rx) = ShutdownSignal::new();
 
             let total = 512;
             for idx in 0..total {
-                tx.send(LogEvent {
-                    message: format!("event-{idx}"),
-                    timestamp: Utc::now(),
-                })
-                .unwrap();
+                tx.send(LogEvent::new(format!("event-{idx}"), Utc::now()));
             }
             drop(tx);
             shutdown_tx.send(shutdown_signal).unwrap();
@@ -354,6 +1282,36 @@ mod tests {
             );
         }
 
+        #[test]
+        fn runs_exporter_task_on_another_runtime() {
+            let client = RecordingClient::default();
+            // Created outside of a runtime: nothing is spawned until the task is.
+            let (dispatcher, guard, task) = BatchExporter::new(
+                client.clone(),
+                ExportConfig::default()
+                    .with_log_group_name("group")
+                    .with_log_stream_name("stream"),
+            )
+            .into_task();
+            let subscriber =
+                tracing_subscriber::registry().with(crate::layer().with_dispatcher(dispatcher));
+            tracing::subscriber::with_default(subscriber, || tracing::info!("from tracing"));
+
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_time()
+                .build()
+                .unwrap();
+            runtime.block_on(async {
+                let exporter = tokio::spawn(task);
+                guard.shutdown().await;
+                exporter.await.unwrap();
+            });
+
+            let messages = client.exported_messages();
+            assert_eq!(messages.len(), 1);
+            assert!(messages[0].contains("from tracing"), "{messages:?}");
+        }
+
         #[tokio::test(flavor = "current_thread")]
         async fn exports_events_with_registry_on_guard_shutdown() {
             let client = RecordingClient::default();
@@ -407,9 +1365,6 @@ mod tests {
                 );
 
             let subscriber = tracing_subscriber::registry().with(cw_layer);
-            // Let the exporter consume the initial immediate interval tick while the queue is empty.
-            sleep(Duration::from_millis(20)).await;
-
             tracing::subscriber::with_default(subscriber, || {
                 tracing::info!("batch-log-1");
                 tracing::info!("batch-log-2");
@@ -419,6 +1374,35 @@ mod tests {
             guard.shutdown().await;
         }
 
+        #[tokio::test(flavor = "current_thread")]
+        async fn exports_immediately_at_flush_level() {
+            let client = RecordingClient::default();
+            let (cw_layer, guard) = crate::layer()
+                .with_code_location(false)
+                .with_target(false)
+                .with_client(
+                    client.clone(),
+                    ExportConfig::default()
+                        .with_batch_size(1024)
+                        .with_interval(Duration::from_secs(60))
+                        .with_flush_level(Level::ERROR)
+                        .with_log_group_name("group")
+                        .with_log_stream_name("stream"),
+                );
+
+            let subscriber = tracing_subscriber::registry().with(cw_layer);
+            tracing::subscriber::with_default(subscriber, || {
+                tracing::warn!("before");
+                tracing::error!("failure");
+            });
+
+            wait_for_exported_count(&client, 2).await;
+            let messages = client.exported_messages();
+            assert!(messages[0].contains("before"), "{messages:?}");
+            assert!(messages[1].contains("failure"), "{messages:?}");
+            guard.shutdown().await;
+        }
+
         #[tokio::test(flavor = "current_thread")]
         async fn exports_without_shutdown_when_batch_not_full() {
             let client = RecordingClient::default();
@@ -435,9 +1419,6 @@ mod tests {
                 );
 
             let subscriber = tracing_subscriber::registry().with(cw_layer);
-            // Let the exporter consume the initial immediate interval tick while the queue is empty.
-            sleep(Duration::from_millis(20)).await;
-
             tracing::subscriber::with_default(subscriber, || {
                 tracing::info!("interval-log-1");
             });
@@ -447,9 +1428,134 @@ mod tests {
         }
     }
 
+    mod reload {
+        use super::helper::{RecordingClient, SizeRecordingClient, wait_for_exported_count};
+        use super::*;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        #[tokio::test(flavor = "current_thread")]
+        async fn splits_the_flush_of_a_burst() {
+            let client = SizeRecordingClient::default();
+            let config = ExportConfig::default()
+                .with_batch_policy(ThresholdPolicy::new())
+                .with_log_group_name("group")
+                .with_log_stream_name("stream");
+            let (reload_tx, reload_rx) = watch::channel(config.clone());
+            let exporter = BatchExporter::new(client.clone(), config).with_reload(reload_rx);
+            let (tx, rx) = queue::event_queue(20_000, None);
+            let (shutdown_tx, shutdown_rx) = oneshot::channel();
+            let worker = tokio::spawn(exporter.run(rx, shutdown_rx));
+
+            for _ in 0..12_000 {
+                tx.send(LogEvent::new("burst", chrono::Utc::now()));
+            }
+            reload_tx.send_modify(|config| *config = config.clone().with_sample_rate(0.5));
+            // The policy never flushes, the reload does.
+            while client.requests.lock().unwrap().is_empty() {
+                tokio::task::yield_now().await;
+            }
+
+            let (shutdown_signal, _ack_rx) = ShutdownSignal::new();
+            shutdown_tx.send(shutdown_signal).unwrap();
+            worker.await.unwrap();
+
+            let requests = client.requests.lock().unwrap();
+            assert!(requests.len() > 1, "{requests:?}");
+            assert!(requests.iter().all(|&(events, _)| events <= MAX_BATCH_SIZE));
+            let events: usize = requests.iter().map(|&(events, _)| events).sum();
+            assert_eq!(events, 12_000);
+        }
+
+        #[tokio::test(flavor = "current_thread")]
+        async fn switches_destination_without_losing_buffered_events() {
+            let client = RecordingClient::default();
+            let (cw_layer, guard) = crate::layer()
+                .with_code_location(false)
+                .with_target(false)
+                .with_client(
+                    client.clone(),
+                    ExportConfig::default()
+                        .with_batch_size(1024)
+                        .with_interval(Duration::from_secs(60))
+                        .with_log_group_name("group")
+                        .with_log_stream_name("stream"),
+                );
+
+            let subscriber = tracing_subscriber::registry().with(cw_layer);
+            let _default = tracing::subscriber::set_default(subscriber);
+
+            tracing::info!("before-reload");
+            guard
+                .reload_handle()
+                .modify(|config| {
+                    *config = config
+                        .clone()
+                        .with_log_stream_name("debug-stream")
+                        .with_batch_size(1)
+                })
+                .unwrap();
+
+            // The buffered event is flushed to the previous stream when the reload is applied.
+            wait_for_exported_count(&client, 1).await;
+            tracing::info!("after-reload");
+            // The new batch size takes effect without waiting for the interval.
+            wait_for_exported_count(&client, 2).await;
+
+            assert_eq!(client.exported_streams(), ["stream", "debug-stream"]);
+            assert_eq!(
+                guard.reload_handle().current().destination.log_stream_name,
+                "debug-stream"
+            );
+            guard.shutdown().await;
+        }
+
+        #[tokio::test(flavor = "current_thread")]
+        async fn rejects_invalid_configs() {
+            let (_cw_layer, guard) = crate::layer::<tracing_subscriber::Registry>().with_client(
+                RecordingClient::default(),
+                ExportConfig::default()
+                    .with_log_group_name("group")
+                    .with_log_stream_name("stream"),
+            );
+            let handle = guard.reload_handle();
+
+            let err = handle
+                .modify(|config| config.sample_rate = f64::NAN)
+                .unwrap_err();
+            assert!(
+                matches!(
+                    err,
+                    crate::ReloadError::InvalidConfig(ConfigError::InvalidSampleRate { .. })
+                ),
+                "{err}"
+            );
+            assert!(handle.reload(ExportConfig::default()).is_err());
+            assert_eq!(handle.current().destination.log_stream_name, "stream");
+
+            handle
+                .modify(|config| *config = config.clone().with_sample_rate(0.1))
+                .unwrap();
+            assert_eq!(handle.current().sample_rate, 0.1);
+            guard.shutdown().await;
+        }
+
+        #[tokio::test(flavor = "current_thread")]
+        async fn fails_after_worker_stopped() {
+            let (_cw_layer, guard) = crate::layer::<tracing_subscriber::Registry>()
+                .with_client(RecordingClient::default(), ExportConfig::default());
+            let handle = guard.reload_handle();
+            guard.shutdown().await;
+
+            assert!(matches!(
+                handle.reload(ExportConfig::default()),
+                Err(crate::ReloadError::WorkerStopped)
+            ));
+        }
+    }
+
     mod recursion {
         use super::*;
-        use async_trait::async_trait;
+        use crate::client::{BoxFuture, PutLogsError};
         use std::sync::{
             Arc, Mutex,
             atomic::{AtomicUsize, Ordering},
@@ -466,22 +1572,22 @@ mod tests {
             put_calls: Arc<AtomicUsize>,
         }
 
-        #[async_trait]
         impl CloudWatchClient for InternalTracingClient {
-            async fn put_logs(
-                &self,
-                _dest: LogDestination,
-                logs: Vec<LogEvent>,
-            ) -> Result<(), crate::client::PutLogsError> {
-                let call = self.put_calls.fetch_add(1, Ordering::Relaxed) + 1;
-                self.logs.lock().unwrap().extend(logs);
-
-                // Simulate SDK traces that recursively trigger additional exports.
-                if call < RECURSIVE_EMIT_LIMIT {
-                    tracing::error!("simulated-sdk-internal-error-{call}");
-                }
+            fn put_logs<'a>(
+                &'a self,
+                batch: LogBatch<'a>,
+            ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+                Box::pin(async move {
+                    let call = self.put_calls.fetch_add(1, Ordering::Relaxed) + 1;
+                    self.logs.lock().unwrap().extend_from_slice(batch.events);
+
+                    // Simulate SDK traces that recursively trigger additional exports.
+                    if call < RECURSIVE_EMIT_LIMIT {
+                        tracing::error!("simulated-sdk-internal-error-{call}");
+                    }
 
-                Ok(())
+                    Ok(())
+                })
             }
         }
 
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use super::error::ExportError;
+use crate::client::middleware::{Admission, CircuitState, Transition};
+
+/// The [`CircuitState`] of the exporter, reporting its state changes as [`ExportError`]s.
+#[derive(Debug, Default)]
+pub(crate) struct Circuit {
+    state: CircuitState,
+    /// Events dropped while the circuit was open.
+    dropped_events: u64,
+}
+
+impl Circuit {
+    pub(crate) fn admit(&mut self, cooldown: Duration) -> Admission {
+        self.state.admit(cooldown)
+    }
+
+    pub(crate) fn reject(&mut self, events: usize) {
+        self.dropped_events += events as u64;
+    }
+
+    /// Record the result of a batch, returning the state change if any.
+    pub(crate) fn record(
+        &mut self,
+        success: bool,
+        failure_threshold: u32,
+        cooldown: Duration,
+    ) -> Option<ExportError> {
+        match self.state.record(success, failure_threshold)? {
+            Transition::Opened {
+                consecutive_failures,
+            } => Some(ExportError::CircuitOpened {
+                consecutive_failures,
+                cooldown,
+            }),
+            Transition::Closed => Some(ExportError::CircuitClosed {
+                dropped_events: std::mem::take(&mut self.dropped_events),
+            }),
+        }
+    }
+}
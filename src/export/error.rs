@@ -0,0 +1,88 @@
+use std::{sync::Arc, time::Duration};
+
+use tracing::subscriber::NoSubscriber;
+
+use crate::{client::PutLogsError, export::LogDestination};
+
+/// Problems and state changes of the exporter, reported to the handler set with
+/// [`ExportConfig::with_error_handler`](crate::ExportConfig::with_error_handler).
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum ExportError {
+    #[error("Unable to put logs to cloudwatch. Error: {error:?} {destination:?}")]
+    PutLogs {
+        error: PutLogsError,
+        destination: LogDestination,
+        /// The number of events in the failed batch.
+        events: usize,
+    },
+    #[error(
+        "Dropped {count} events because the queue was full. Consider increasing the queue capacity"
+    )]
+    QueueFull { count: u64 },
+    #[error(
+        "Shed {trace} TRACE, {debug} DEBUG and {info} INFO events because the queue was above its watermarks"
+    )]
+    EventsShed { trace: u64, debug: u64, info: u64 },
+    #[error("Dropped oversized log event ({bytes} bytes). Total dropped: {total}")]
+    OversizedEventDropped { bytes: usize, total: u64 },
+    #[error(
+        "Circuit breaker opened after {consecutive_failures} consecutive failures, dropping batches for {cooldown:?}"
+    )]
+    CircuitOpened {
+        consecutive_failures: u32,
+        cooldown: Duration,
+    },
+    /// Passed to the [`DeadLetterSink`](crate::DeadLetterSink) for batches dropped while the
+    /// circuit breaker is open. Not reported to the error handler.
+    #[error("Circuit breaker is open, the batch was not sent")]
+    CircuitOpen,
+    #[error("Circuit breaker half-open, sending a probe batch")]
+    CircuitHalfOpen,
+    #[error("Circuit breaker closed, {dropped_events} events were dropped while it was open")]
+    CircuitClosed { dropped_events: u64 },
+    #[error("Exporter panicked: {message}. Restarting (restart {restarts})")]
+    WorkerRestarting { message: String, restarts: u32 },
+    #[error("Exporter panicked: {message}. Too many restarts, no more logs will be exported")]
+    WorkerDead { message: String },
+}
+
+/// Callback receiving [`ExportError`]s. Prints them to stderr by default.
+#[derive(Clone)]
+pub(crate) struct ErrorHandler(Arc<dyn Fn(&ExportError) + Send + Sync>);
+
+impl ErrorHandler {
+    pub(crate) fn new(handler: impl Fn(&ExportError) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(handler))
+    }
+
+    pub(crate) fn report(&self, error: ExportError) {
+        (self.0)(&error)
+    }
+}
+
+impl Default for ErrorHandler {
+    /// Emit errors to the diagnostics subscriber of the exporter if any, or print them to stderr.
+    fn default() -> Self {
+        Self::new(|error| {
+            let has_diagnostics =
+                tracing::dispatcher::get_default(|dispatch| !dispatch.is::<NoSubscriber>());
+            if !has_diagnostics {
+                eprintln!("[tracing-cloudwatch] {error}");
+                return;
+            }
+            match error {
+                ExportError::CircuitHalfOpen | ExportError::CircuitClosed { .. } => {
+                    tracing::info!("{error}")
+                }
+                _ => tracing::warn!("{error}"),
+            }
+        })
+    }
+}
+
+impl std::fmt::Debug for ErrorHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ErrorHandler")
+    }
+}
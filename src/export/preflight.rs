@@ -0,0 +1,164 @@
+use chrono::Utc;
+
+use crate::{
+    client::{CloudWatchClient, LogBatch, PutLogsError},
+    dispatch::LogEvent,
+    export::{ConfigError, ExportConfig},
+};
+
+/// The message of the event sent by [`ExportConfig::validate`].
+const CANARY_MESSAGE: &str = "tracing-cloudwatch preflight check";
+
+impl ExportConfig {
+    /// Check that logs can be exported with `client`, e.g. at start-up before creating the layer.
+    ///
+    /// The configuration is checked like [`ExportConfig::try_build`], then the log group and stream
+    /// are created if they do not exist with [`CloudWatchClient::ensure_destination`], and a single
+    /// event is sent to the stream.
+    /// The event requires `logs:PutLogEvents`. Creating the destination requires
+    /// `logs:CreateLogGroup` and `logs:CreateLogStream`, but a role without them passes if the
+    /// destination already exists: a failure to create it is only reported if the event fails too.
+    ///
+    /// ```rust,no_run
+    /// # async fn run(client: impl tracing_cloudwatch::CloudWatchClient) {
+    /// let config = tracing_cloudwatch::ExportConfig::default()
+    ///     .with_log_group_name("tracing-cloudwatch")
+    ///     .with_log_stream_name("stream-1");
+    /// if let Err(err) = config.validate(&client).await {
+    ///     eprintln!("logs will not reach cloudwatch: {err}");
+    /// }
+    /// # }
+    /// ```
+    pub async fn validate<C>(&self, client: &C) -> Result<(), PreflightError>
+    where
+        C: CloudWatchClient + ?Sized,
+    {
+        self.check()?;
+
+        let destination = &self.destination;
+        let create = client.ensure_destination(destination).await.err();
+
+        let events = [LogEvent::new(CANARY_MESSAGE, Utc::now())];
+        let Err(put_logs) = client
+            .put_logs(LogBatch::new(destination, &events, 0))
+            .await
+        else {
+            return Ok(());
+        };
+        Err(match create {
+            Some(create) => PreflightError::Destination { create, put_logs },
+            None => PreflightError::PutLogEvents(put_logs),
+        })
+    }
+}
+
+/// Error of [`ExportConfig::validate`].
+#[derive(Debug, thiserror::Error)]
+pub enum PreflightError {
+    #[error(transparent)]
+    InvalidConfig(#[from] ConfigError),
+    /// The log group or log stream could not be created, and the event could not be sent.
+    #[error(
+        "unable to create the log group or log stream ({create}), then to put log events: {put_logs}"
+    )]
+    Destination {
+        create: PutLogsError,
+        #[source]
+        put_logs: PutLogsError,
+    },
+    #[error("unable to put log events: {0}")]
+    PutLogEvents(#[source] PutLogsError),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{client::BoxFuture, export::LogDestination};
+
+    #[derive(Default)]
+    struct RecordingClient {
+        calls: Mutex<Vec<&'static str>>,
+        missing_permission: bool,
+        missing_destination: bool,
+    }
+
+    impl CloudWatchClient for RecordingClient {
+        fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+            assert_eq!(batch.events[0].message, CANARY_MESSAGE);
+            self.calls.lock().unwrap().push("put_logs");
+            let missing_destination = self.missing_destination;
+            Box::pin(async move {
+                if missing_destination {
+                    Err(anyhow::anyhow!("ResourceNotFoundException").into())
+                } else {
+                    Ok(())
+                }
+            })
+        }
+
+        fn ensure_destination<'a>(
+            &'a self,
+            _: &'a LogDestination,
+        ) -> BoxFuture<'a, Result<(), PutLogsError>> {
+            self.calls.lock().unwrap().push("ensure_destination");
+            let missing_permission = self.missing_permission;
+            Box::pin(async move {
+                if missing_permission {
+                    Err(anyhow::anyhow!("AccessDeniedException").into())
+                } else {
+                    Ok(())
+                }
+            })
+        }
+    }
+
+    fn config() -> ExportConfig {
+        ExportConfig::default()
+            .with_log_group_name("group")
+            .with_log_stream_name("stream")
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn validates_destination_and_sends_canary() {
+        let client = RecordingClient::default();
+        config().validate(&client).await.unwrap();
+        assert_eq!(
+            *client.calls.lock().unwrap(),
+            ["ensure_destination", "put_logs"]
+        );
+
+        // A role allowed to put events to an existing destination only.
+        let client = RecordingClient {
+            missing_permission: true,
+            ..Default::default()
+        };
+        config().validate(&client).await.unwrap();
+        assert_eq!(
+            *client.calls.lock().unwrap(),
+            ["ensure_destination", "put_logs"]
+        );
+
+        let client = RecordingClient {
+            missing_permission: true,
+            missing_destination: true,
+            ..Default::default()
+        };
+        let err = config().validate(&client).await.unwrap_err();
+        assert!(matches!(err, PreflightError::Destination { .. }), "{err}");
+
+        let err = config()
+            .with_log_stream_name("a:b")
+            .validate(&client)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                PreflightError::InvalidConfig(ConfigError::InvalidLogStreamName { .. })
+            ),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use std::{any::Any, future::Future, panic::AssertUnwindSafe, task::Poll, time::Duration};
+
+use tokio::{
+    sync::{oneshot, watch},
+    time::Instant,
+};
+
+use super::{BatchExporter, ExportConfig, error::ExportError};
+use crate::{
+    CloudWatchClient,
+    guard::{ShutdownSignal, WorkerHealth},
+    queue::EventReceiver,
+};
+
+/// Give up after this many restarts in a row.
+const MAX_RESTARTS: u32 = 5;
+/// A run lasting longer than this resets the restart count.
+const RESTART_WINDOW: Duration = Duration::from_secs(60);
+/// Wait this long times the restart count before restarting.
+const RESTART_BACKOFF: Duration = Duration::from_millis(100);
+
+impl<C> BatchExporter<C>
+where
+    C: CloudWatchClient + Send + Sync + 'static,
+{
+    /// Export events until shutdown, restarting the export loop if it panics.
+    ///
+    /// Events waiting in the exporter are kept across restarts. After [`MAX_RESTARTS`] panics in a
+    /// row, the remaining events are dead-lettered and the worker stops.
+    pub(crate) async fn run(
+        mut self,
+        mut rx: EventReceiver,
+        mut shutdown_rx: oneshot::Receiver<ShutdownSignal>,
+    ) {
+        let mut reload_rx: Option<watch::Receiver<ExportConfig>> = self.reload_rx.take();
+        let mut restarts = 0;
+        self.health.set(WorkerHealth::Running);
+
+        loop {
+            let started = Instant::now();
+            let result =
+                catch_unwind(self.run_loop(&mut rx, &mut shutdown_rx, &mut reload_rx)).await;
+            let Err(panic) = result else {
+                break;
+            };
+
+            let message = panic_message(&*panic);
+            if started.elapsed() >= RESTART_WINDOW {
+                restarts = 0;
+            }
+            restarts += 1;
+            if restarts > MAX_RESTARTS {
+                self.health.set(WorkerHealth::Dead);
+                let error = ExportError::WorkerDead { message };
+                self.dead_letter(&self.queue, &error);
+                self.config.error_handler.report(error);
+                return;
+            }
+
+            self.health.set(WorkerHealth::Restarting);
+            self.config
+                .error_handler
+                .report(ExportError::WorkerRestarting { message, restarts });
+            tokio::time::sleep(RESTART_BACKOFF * restarts).await;
+            self.health.set(WorkerHealth::Running);
+        }
+
+        self.health.set(WorkerHealth::Stopped);
+        if let Some(shutdown_signal) = self.shutdown_signal.take() {
+            shutdown_signal.ack();
+        }
+    }
+}
+
+/// Resolve to the output of `future`, or to the panic payload if polling it panics.
+pub(super) async fn catch_unwind<F: Future>(future: F) -> Result<F::Output, Box<dyn Any + Send>> {
+    let mut future = std::pin::pin!(future);
+    std::future::poll_fn(|cx| {
+        match std::panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(panic) => Poll::Ready(Err(panic)),
+        }
+    })
+    .await
+}
+
+pub(super) fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
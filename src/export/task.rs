@@ -0,0 +1,103 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::sync::oneshot;
+use tracing::instrument::WithSubscriber;
+
+use super::BatchExporter;
+use crate::{
+    CloudWatchClient,
+    dispatch::{CloudWatchDispatcher, EXPORTER_QUEUE},
+    guard::{CloudWatchWorkerGuard, HealthState},
+    queue,
+    reload::ReloadHandle,
+};
+
+impl<C> BatchExporter<C>
+where
+    C: CloudWatchClient + Send + Sync + 'static,
+{
+    /// Split the exporter into the [`CloudWatchDispatcher`] sending it events, the guard shutting
+    /// it down and the [`ExporterTask`] exporting the events, without spawning it.
+    ///
+    /// The task can be spawned on the runtime of your choice, which must be a tokio runtime with
+    /// the time driver enabled, or awaited by your own task supervisor.
+    ///
+    /// ```rust,no_run
+    /// # async fn run(client: impl tracing_cloudwatch::CloudWatchClient + 'static) {
+    /// use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+    ///
+    /// let config = tracing_cloudwatch::ExportConfig::default()
+    ///     .with_log_group_name("tracing-cloudwatch")
+    ///     .with_log_stream_name("stream-1");
+    /// let (dispatcher, guard, task) = tracing_cloudwatch::BatchExporter::new(client, config).into_task();
+    /// let exporter = tokio::spawn(task);
+    ///
+    /// tracing_subscriber::registry()
+    ///     .with(tracing_cloudwatch::layer().with_dispatcher(dispatcher))
+    ///     .init();
+    ///
+    /// guard.shutdown().await;
+    /// exporter.await.unwrap();
+    /// # }
+    /// ```
+    pub fn into_task(self) -> (CloudWatchDispatcher, CloudWatchWorkerGuard, ExporterTask) {
+        let (tx, rx) =
+            queue::event_queue(self.config.queue_capacity.get(), self.config.load_shedding);
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let (reload_handle, reload_rx) = ReloadHandle::new(self.config.clone());
+        let health = HealthState::default();
+        let guard = CloudWatchWorkerGuard::new(shutdown_tx, reload_handle, health.clone());
+
+        // Override the subscriber for the exporter to prevent recursively
+        // tracing new events from sdk calls within the exporter
+        let diagnostics = self
+            .config
+            .diagnostics
+            .clone()
+            .unwrap_or_else(tracing::dispatcher::Dispatch::none);
+        let run = self
+            .with_reload(reload_rx)
+            .with_health(health)
+            .run(rx, shutdown_rx)
+            .with_subscriber(diagnostics);
+        let task = ExporterTask(Box::pin(EXPORTER_QUEUE.scope(tx.id(), run)));
+
+        (CloudWatchDispatcher::new(tx), guard, task)
+    }
+
+    /// Spawn the exporter on the current tokio runtime, see [`Self::into_task`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn spawn(self) -> (CloudWatchDispatcher, CloudWatchWorkerGuard) {
+        let (dispatcher, guard, task) = self.into_task();
+        tokio::spawn(task);
+        (dispatcher, guard)
+    }
+}
+
+/// Future exporting events until the [`CloudWatchWorkerGuard`] shuts it down or is dropped,
+/// returned by [`BatchExporter::into_task`].
+///
+/// It completes once the remaining events are flushed.
+#[must_use = "events are only exported while the task is polled"]
+pub struct ExporterTask(Pin<Box<dyn Future<Output = ()> + Send>>);
+
+impl Future for ExporterTask {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.0.as_mut().poll(cx)
+    }
+}
+
+impl std::fmt::Debug for ExporterTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ExporterTask")
+    }
+}
@@ -0,0 +1,268 @@
+use std::{num::NonZeroUsize, time::Duration};
+
+use crate::export::ExportConfig;
+
+/// The maximum number of events in a `PutLogEvents` request.
+pub(crate) const MAX_BATCH_SIZE: usize = 10_000;
+
+/// The maximum time span of the events in a `PutLogEvents` request.
+const MAX_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The maximum length of log group and log stream names.
+const MAX_NAME_LENGTH: usize = 512;
+
+/// Errors raised by an invalid configuration.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum ConfigError {
+    #[error("invalid value {value:?} for environment variable {name}: {reason}")]
+    InvalidEnvVar {
+        name: String,
+        value: String,
+        reason: String,
+    },
+    #[cfg(feature = "config")]
+    #[error("invalid filter directive: {0}")]
+    InvalidFilter(#[from] tracing_subscriber::filter::ParseError),
+    #[error("batch size {batch_size} must be between 1 and {MAX_BATCH_SIZE}")]
+    InvalidBatchSize { batch_size: usize },
+    #[error("queue capacity must be greater than or equal to 1")]
+    InvalidQueueCapacity,
+    #[error("interval {interval:?} must be greater than zero and at most 24 hours")]
+    InvalidInterval { interval: Duration },
+    #[error("invalid log group name {name:?}: {reason}")]
+    InvalidLogGroupName { name: String, reason: &'static str },
+    #[error("invalid log stream name {name:?}: {reason}")]
+    InvalidLogStreamName { name: String, reason: &'static str },
+    #[error(
+        "queue capacity {queue_capacity} is smaller than batch size {batch_size}, so batches are never full"
+    )]
+    QueueCapacityBelowBatchSize {
+        queue_capacity: usize,
+        batch_size: usize,
+    },
+    #[error("load shedding watermarks must be at most 100 percent and increase from TRACE to INFO")]
+    InvalidLoadShedding,
+    #[error("sample rate {sample_rate} must be between 0.0 and 1.0")]
+    InvalidSampleRate { sample_rate: f64 },
+}
+
+impl ExportConfig {
+    /// Set batch size, failing unless it is between 1 and 10000.
+    /// See [`Self::with_batch_size`] for a panicking version.
+    pub fn try_with_batch_size(self, batch_size: usize) -> Result<Self, ConfigError> {
+        match NonZeroUsize::new(batch_size) {
+            Some(batch_size) if batch_size.get() <= MAX_BATCH_SIZE => {
+                Ok(Self { batch_size, ..self })
+            }
+            _ => Err(ConfigError::InvalidBatchSize { batch_size }),
+        }
+    }
+
+    /// Set how many events can wait for the exporter, failing if it is zero.
+    /// See [`Self::with_queue_capacity`] for a panicking version.
+    pub fn try_with_queue_capacity(self, queue_capacity: usize) -> Result<Self, ConfigError> {
+        let queue_capacity =
+            NonZeroUsize::new(queue_capacity).ok_or(ConfigError::InvalidQueueCapacity)?;
+        Ok(Self {
+            queue_capacity,
+            ..self
+        })
+    }
+
+    /// Check the configuration, e.g. at the end of a chain of `with_*` calls, so mistakes fail at
+    /// start-up instead of the first flush.
+    ///
+    /// - the batch size is at most 10000, the `PutLogEvents` limit
+    /// - the interval is greater than zero and at most 24 hours, the longest time span of a batch
+    /// - the log group and stream names follow the CloudWatch Logs naming rules
+    /// - the queue capacity is not smaller than the batch size
+    /// - the load shedding watermarks are at most 100 percent and increase from TRACE to INFO
+    ///
+    /// ```rust
+    /// let config = tracing_cloudwatch::ExportConfig::default()
+    ///     .with_log_group_name("tracing-cloudwatch")
+    ///     .with_log_stream_name("stream-1")
+    ///     .try_with_batch_size(100)?
+    ///     .try_build()?;
+    /// # let _ = config;
+    /// # Ok::<(), tracing_cloudwatch::ConfigError>(())
+    /// ```
+    pub fn try_build(self) -> Result<Self, ConfigError> {
+        self.check()?;
+        Ok(self)
+    }
+
+    pub(crate) fn check(&self) -> Result<(), ConfigError> {
+        let batch_size = self.batch_size.get();
+        if batch_size > MAX_BATCH_SIZE {
+            return Err(ConfigError::InvalidBatchSize { batch_size });
+        }
+        if self.interval.is_zero() || self.interval > MAX_INTERVAL {
+            return Err(ConfigError::InvalidInterval {
+                interval: self.interval,
+            });
+        }
+
+        let destination = &self.destination;
+        check_log_group_name(&destination.log_group_name).map_err(|reason| {
+            ConfigError::InvalidLogGroupName {
+                name: destination.log_group_name.clone(),
+                reason,
+            }
+        })?;
+        check_log_stream_name(&destination.log_stream_name).map_err(|reason| {
+            ConfigError::InvalidLogStreamName {
+                name: destination.log_stream_name.clone(),
+                reason,
+            }
+        })?;
+
+        let queue_capacity = self.queue_capacity.get();
+        if queue_capacity < batch_size {
+            return Err(ConfigError::QueueCapacityBelowBatchSize {
+                queue_capacity,
+                batch_size,
+            });
+        }
+        if self
+            .load_shedding
+            .is_some_and(|load_shedding| !load_shedding.is_valid())
+        {
+            return Err(ConfigError::InvalidLoadShedding);
+        }
+        if !(0.0..=1.0).contains(&self.sample_rate) {
+            return Err(ConfigError::InvalidSampleRate {
+                sample_rate: self.sample_rate,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Check a log group name against the CloudWatch Logs naming rules.
+fn check_log_group_name(name: &str) -> Result<(), &'static str> {
+    check_length(name)?;
+    if name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '/' | '#'))
+    {
+        Ok(())
+    } else {
+        Err("only a-z, A-Z, 0-9, '.', '-', '_', '/' and '#' are allowed")
+    }
+}
+
+/// Check a log stream name against the CloudWatch Logs naming rules.
+fn check_log_stream_name(name: &str) -> Result<(), &'static str> {
+    check_length(name)?;
+    if name.contains([':', '*']) {
+        Err("':' and '*' are not allowed")
+    } else {
+        Ok(())
+    }
+}
+
+fn check_length(name: &str) -> Result<(), &'static str> {
+    match name.chars().count() {
+        0 => Err("must not be empty"),
+        length if length > MAX_NAME_LENGTH => Err("must be at most 512 characters"),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ExportConfig {
+        ExportConfig::default()
+            .with_log_group_name("group")
+            .with_log_stream_name("stream")
+    }
+
+    #[test]
+    fn checks_names() {
+        assert!(check_log_group_name("/aws/lambda/my-func_1.#").is_ok());
+        assert!(check_log_group_name("").is_err());
+        assert!(check_log_group_name("group name").is_err());
+        assert!(check_log_group_name(&"a".repeat(513)).is_err());
+
+        assert!(check_log_stream_name("2024/01/01/[$LATEST] abc").is_ok());
+        assert!(check_log_stream_name("stream:1").is_err());
+        assert!(check_log_stream_name("stream*").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_configs() {
+        assert!(config().try_build().is_ok());
+
+        let err = config().try_with_batch_size(0).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::InvalidBatchSize { batch_size: 0 }
+        ));
+        let err = config().with_batch_size(10_001).try_build().unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::InvalidBatchSize { batch_size: 10_001 }
+        ));
+
+        let err = config()
+            .with_interval(Duration::ZERO)
+            .try_build()
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidInterval { .. }));
+
+        let err = ExportConfig::default().try_build().unwrap_err();
+        assert!(
+            matches!(err, ConfigError::InvalidLogGroupName { .. }),
+            "{err}"
+        );
+        let err = config()
+            .with_log_stream_name("a*b")
+            .try_build()
+            .unwrap_err();
+        assert!(
+            matches!(err, ConfigError::InvalidLogStreamName { .. }),
+            "{err}"
+        );
+
+        let err = config()
+            .with_load_shedding(crate::LoadShedding::new().with_trace_watermark(90))
+            .try_build()
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidLoadShedding), "{err}");
+
+        // Only reachable through deserialization, the builder clamps the rate.
+        let err = ExportConfig {
+            sample_rate: -0.5,
+            ..config()
+        }
+        .try_build()
+        .unwrap_err();
+        assert!(
+            matches!(err, ConfigError::InvalidSampleRate { .. }),
+            "{err}"
+        );
+        assert_eq!(config().with_sample_rate(1.5).sample_rate, 1.0);
+        assert_eq!(config().with_sample_rate(-1.0).sample_rate, 0.0);
+        assert_eq!(config().with_sample_rate(f64::NAN).sample_rate, 1.0);
+
+        let err = config()
+            .with_batch_size(100)
+            .with_queue_capacity(10)
+            .try_build()
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                ConfigError::QueueCapacityBelowBatchSize {
+                    queue_capacity: 10,
+                    batch_size: 100
+                }
+            ),
+            "{err}"
+        );
+    }
+}
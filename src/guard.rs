@@ -1,5 +1,12 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU8, Ordering},
+};
+
 use tokio::sync::oneshot;
 
+use crate::reload::ReloadHandle;
+
 #[derive(Debug)]
 pub(crate) struct ShutdownSignal {
     ack_tx: oneshot::Sender<()>,
@@ -16,6 +23,37 @@ impl ShutdownSignal {
     }
 }
 
+/// State of the worker exporting logs, see [`CloudWatchWorkerGuard::health`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerHealth {
+    Running,
+    /// The worker panicked and is about to restart. Events are kept in the meantime.
+    Restarting,
+    /// The worker panicked too many times in a row and stopped. Events are no longer exported.
+    Dead,
+    /// The worker shut down.
+    Stopped,
+}
+
+/// [`WorkerHealth`] shared between the worker and its guard.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HealthState(Arc<AtomicU8>);
+
+impl HealthState {
+    pub(crate) fn set(&self, health: WorkerHealth) {
+        self.0.store(health as u8, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get(&self) -> WorkerHealth {
+        match self.0.load(Ordering::Relaxed) {
+            0 => WorkerHealth::Running,
+            1 => WorkerHealth::Restarting,
+            2 => WorkerHealth::Dead,
+            _ => WorkerHealth::Stopped,
+        }
+    }
+}
+
 /// Guard returned when creating a CloudWatch layer
 ///
 /// When this guard is dropped a shutdown signal will be
@@ -27,15 +65,33 @@ impl ShutdownSignal {
 /// to explicitly wait for completion.
 pub struct CloudWatchWorkerGuard {
     shutdown_tx: Option<oneshot::Sender<ShutdownSignal>>,
+    reload_handle: ReloadHandle,
+    health: HealthState,
 }
 
 impl CloudWatchWorkerGuard {
-    pub(crate) fn new(shutdown_tx: oneshot::Sender<ShutdownSignal>) -> Self {
+    pub(crate) fn new(
+        shutdown_tx: oneshot::Sender<ShutdownSignal>,
+        reload_handle: ReloadHandle,
+        health: HealthState,
+    ) -> Self {
         Self {
             shutdown_tx: Some(shutdown_tx),
+            reload_handle,
+            health,
         }
     }
 
+    /// Return the state of the worker, e.g. to alert when it is [`WorkerHealth::Dead`].
+    pub fn health(&self) -> WorkerHealth {
+        self.health.get()
+    }
+
+    /// Return a handle to change the export configuration of the running worker.
+    pub fn reload_handle(&self) -> ReloadHandle {
+        self.reload_handle.clone()
+    }
+
     fn take_shutdown_tx(&mut self) -> Option<oneshot::Sender<ShutdownSignal>> {
         self.shutdown_tx.take()
     }
@@ -78,7 +134,8 @@ mod tests {
     #[tokio::test(flavor = "current_thread")]
     async fn shutdown_waits_for_ack() {
         let (shutdown_tx, shutdown_rx) = oneshot::channel::<ShutdownSignal>();
-        let guard = CloudWatchWorkerGuard::new(shutdown_tx);
+        let (reload_handle, _reload_rx) = ReloadHandle::new(Default::default());
+        let guard = CloudWatchWorkerGuard::new(shutdown_tx, reload_handle, HealthState::default());
 
         let worker = tokio::spawn(async move {
             let signal = shutdown_rx.await.unwrap();
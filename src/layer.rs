@@ -1,23 +1,32 @@
 use std::sync::Arc;
 
-use tracing_core::{Event, Subscriber, span};
+use chrono::{DateTime, Utc};
+use tracing_core::{Event, Field, Subscriber, field::Visit, span};
 use tracing_subscriber::{
     Layer,
-    fmt::{self, MakeWriter, format},
+    fmt::{self, MakeWriter, format, format::FmtSpan},
     layer::Context,
     registry::LookupSpan,
 };
 
 use crate::{
     client::CloudWatchClient,
-    dispatch::{CloudWatchDispatcher, Dispatcher, NoopDispatcher},
-    export::ExportConfig,
-    guard::{CloudWatchWorkerGuard, ShutdownSignal},
+    clock::{Clock, SystemClock},
+    deferred::{self, EventFormatter},
+    dispatch::{self, CloudWatchDispatcher, Dispatcher, LogEvent, NoopDispatcher},
+    export::{BatchExporter, ExportConfig},
+    guard::CloudWatchWorkerGuard,
+    span::SpanRecorder,
 };
 
 /// An AWS Cloudwatch propagation layer.
 pub struct CloudWatchLayer<S, D, N = format::DefaultFields, E = format::Format<format::Full, ()>> {
     fmt_layer: fmt::Layer<S, N, E, Arc<D>>,
+    clock: Arc<dyn Clock>,
+    timestamp_from_field: bool,
+    span_recorder: SpanRecorder,
+    deferred_formatter: Option<Arc<dyn EventFormatter>>,
+    structured_events: bool,
 }
 
 /// Construct [CloudWatchLayer] to compose with tracing subscriber.
@@ -54,6 +63,11 @@ where
                 .with_line_number(true)
                 .with_file(true)
                 .with_target(false),
+            clock: Arc::new(SystemClock),
+            timestamp_from_field: false,
+            span_recorder: SpanRecorder::new(FmtSpan::NONE),
+            deferred_formatter: None,
+            structured_events: false,
         }
     }
 }
@@ -67,6 +81,7 @@ where
     pub fn with_code_location(self, display: bool) -> Self {
         Self {
             fmt_layer: self.fmt_layer.with_line_number(display).with_file(display),
+            ..self
         }
     }
 
@@ -75,6 +90,7 @@ where
     pub fn with_target(self, display: bool) -> Self {
         Self {
             fmt_layer: self.fmt_layer.with_target(display),
+            ..self
         }
     }
 }
@@ -97,22 +113,32 @@ where
     where
         Client: CloudWatchClient + Send + Sync + 'static,
     {
-        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<ShutdownSignal>();
+        let (dispatcher, guard) = BatchExporter::new(client, export_config).spawn();
+        (self.with_dispatcher(dispatcher), guard)
+    }
 
-        let guard = CloudWatchWorkerGuard::new(shutdown_tx);
+    /// Export events through `dispatcher`, e.g. of an exporter run with
+    /// [`BatchExporter::into_task`] instead of being spawned by [`Self::with_client`].
+    pub fn with_dispatcher(
+        self,
+        dispatcher: CloudWatchDispatcher,
+    ) -> CloudWatchLayer<S, CloudWatchDispatcher, N, E> {
+        CloudWatchLayer {
+            fmt_layer: self.fmt_layer.with_writer(Arc::new(dispatcher)),
+            clock: self.clock,
+            timestamp_from_field: self.timestamp_from_field,
+            span_recorder: self.span_recorder,
+            deferred_formatter: self.deferred_formatter,
+            structured_events: self.structured_events,
+        }
+    }
 
-        (
-            CloudWatchLayer {
-                fmt_layer: self
-                    .fmt_layer
-                    .with_writer(Arc::new(CloudWatchDispatcher::new(
-                        client,
-                        export_config,
-                        shutdown_rx,
-                    ))),
-            },
-            guard,
-        )
+    /// Return the dispatcher shared by this layer.
+    ///
+    /// Useful to rebuild the layer with other formatter options through [`tracing_subscriber::reload`]
+    /// without restarting the exporter.
+    pub fn dispatcher(&self) -> Arc<D> {
+        self.fmt_layer.writer().clone()
     }
 
     /// Set the [`fmt::Layer`] provided as an argument.
@@ -125,10 +151,141 @@ where
         let writer = self.fmt_layer.writer().clone();
         CloudWatchLayer {
             fmt_layer: fmt_layer.with_writer(writer),
+            clock: self.clock,
+            timestamp_from_field: self.timestamp_from_field,
+            span_recorder: self.span_recorder,
+            deferred_formatter: self.deferred_formatter,
+            structured_events: self.structured_events,
+        }
+    }
+
+    /// Set the [`Clock`] used to timestamp events when they happen.
+    /// Default [`SystemClock`].
+    pub fn with_clock(self, clock: impl Clock + 'static) -> Self {
+        Self {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+
+    /// Configure which span lifecycle events are exported as structured JSON records.
+    /// Default [`FmtSpan::NONE`].
+    ///
+    /// Each record contains the span ID, parent ID, name, target and fields.
+    /// `close` records also contain the busy, idle and total duration of the span in nanoseconds,
+    /// so latency breakdowns can be built with Logs Insights, e.g.
+    /// `filter span_event = "close" | stats avg(duration_ns) by name`.
+    pub fn with_span_events(self, kind: FmtSpan) -> Self {
+        Self {
+            span_recorder: SpanRecorder::new(kind),
+            ..self
+        }
+    }
+
+    /// Format events on the exporter task instead of the application thread.
+    ///
+    /// Events are captured into an [`EventRecord`](crate::EventRecord) holding their fields and
+    /// the fields of their spans, and `formatter` turns each record into a message when the
+    /// exporter receives it. This keeps serialization, redaction and enrichment off
+    /// latency-sensitive threads; only `Debug` field values are still formatted on the caller.
+    ///
+    /// The [`fmt::Layer`] options, such as [`with_code_location`](Self::with_code_location),
+    /// no longer apply to events, configure the formatter instead, e.g. [`JsonFormatter`](crate::JsonFormatter).
+    /// Span lifecycle events are not deferred.
+    pub fn with_deferred_formatting(self, formatter: impl EventFormatter + 'static) -> Self {
+        Self {
+            deferred_formatter: Some(Arc::new(formatter)),
+            ..self
+        }
+    }
+
+    /// Attach an [`EventRecord`](crate::EventRecord) with the fields and the span stack to the
+    /// formatted events, see [`LogEvent::record`](crate::LogEvent::record). Default false.
+    ///
+    /// The fields are captured on the application thread besides formatting the message, so
+    /// `Debug` values are formatted twice. Events always carry a record with deferred formatting.
+    pub fn with_structured_events(self, enabled: bool) -> Self {
+        Self {
+            structured_events: enabled,
+            ..self
+        }
+    }
+
+    /// Configure to take the timestamp from a `timestamp` field of the event if present,
+    /// e.g. when replaying events. The field is either epoch milliseconds or an RFC 3339 string.
+    /// Default false.
+    pub fn with_timestamp_from_field(self, enabled: bool) -> Self {
+        Self {
+            timestamp_from_field: enabled,
+            ..self
         }
     }
 }
 
+impl<S, D, N, E> CloudWatchLayer<S, D, N, E>
+where
+    D: Dispatcher,
+{
+    fn event_timestamp(&self, event: &Event<'_>) -> DateTime<Utc> {
+        if self.timestamp_from_field {
+            let mut visitor = TimestampVisitor(None);
+            event.record(&mut visitor);
+            if let Some(timestamp) = visitor.0 {
+                return timestamp;
+            }
+        }
+        self.clock.now()
+    }
+
+    /// Whether events carry an [`EventRecord`](crate::EventRecord), so span fields are kept.
+    fn records_events(&self) -> bool {
+        self.structured_events || self.deferred_formatter.is_some()
+    }
+
+    fn dispatch_record(&self, record: Option<String>) {
+        if let Some(message) = record {
+            self.fmt_layer
+                .writer()
+                .dispatch(LogEvent::new(message, self.clock.now()));
+        }
+    }
+
+    /// Run `f` and dispatch everything it writes as a single event.
+    fn dispatch_writes(&self, timestamp: DateTime<Utc>, f: impl FnOnce()) {
+        let dispatcher = self.fmt_layer.writer().as_ref();
+        if let Some(event) = dispatch::capture_event(dispatcher, timestamp, f) {
+            dispatcher.dispatch(event);
+        }
+    }
+}
+
+/// Visit the `timestamp` field of an event.
+struct TimestampVisitor(Option<DateTime<Utc>>);
+
+impl Visit for TimestampVisitor {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        if field.name() == "timestamp" {
+            self.0 = DateTime::from_timestamp_millis(value);
+        }
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if let Ok(value) = i64::try_from(value) {
+            self.record_i64(field, value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "timestamp" {
+            self.0 = DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|timestamp| timestamp.with_timezone(&Utc));
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+}
+
 impl<S, D, N, E> Layer<S> for CloudWatchLayer<S, D, N, E>
 where
     S: Subscriber + for<'span> LookupSpan<'span>,
@@ -138,10 +295,29 @@ where
     E: format::FormatEvent<S, N> + 'static,
 {
     fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
-        self.fmt_layer.on_enter(id, ctx)
+        self.dispatch_record(self.span_recorder.on_enter(id, &ctx));
+        self.dispatch_writes(self.clock.now(), || self.fmt_layer.on_enter(id, ctx))
     }
     fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
-        self.fmt_layer.on_event(event, ctx)
+        // Capture the timestamp before formatting, so it reflects when the event happened.
+        let timestamp = self.event_timestamp(event);
+        if let Some(formatter) = &self.deferred_formatter {
+            let record = deferred::capture_event(event, &ctx, timestamp);
+            self.fmt_layer.writer().dispatch_deferred(record, formatter);
+            return;
+        }
+        let record = self
+            .structured_events
+            .then(|| deferred::capture_event(event, &ctx, timestamp));
+        let dispatcher = self.fmt_layer.writer().as_ref();
+        if let Some(captured) = dispatch::capture_event(dispatcher, timestamp, || {
+            self.fmt_layer.on_event(event, ctx)
+        }) {
+            dispatcher.dispatch(match record {
+                Some(record) => captured.with_record(record),
+                None => captured.with_metadata(event.metadata()),
+            });
+        }
     }
 
     fn on_register_dispatch(&self, collector: &tracing::Dispatch) {
@@ -157,10 +333,20 @@ where
     }
 
     fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
-        self.fmt_layer.on_new_span(attrs, id, ctx)
+        if self.records_events() {
+            deferred::on_new_span(attrs, id, &ctx);
+        }
+        self.dispatch_record(self.span_recorder.on_new_span(attrs, id, &ctx));
+        self.dispatch_writes(self.clock.now(), || {
+            self.fmt_layer.on_new_span(attrs, id, ctx)
+        })
     }
 
     fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        self.span_recorder.on_record(id, values, &ctx);
+        if self.records_events() {
+            deferred::on_record(id, values, &ctx);
+        }
         self.fmt_layer.on_record(id, values, ctx)
     }
 
@@ -173,11 +359,13 @@ where
     }
 
     fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
-        self.fmt_layer.on_exit(id, ctx)
+        self.dispatch_record(self.span_recorder.on_exit(id, &ctx));
+        self.dispatch_writes(self.clock.now(), || self.fmt_layer.on_exit(id, ctx))
     }
 
     fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
-        self.fmt_layer.on_close(id, ctx)
+        self.dispatch_record(self.span_recorder.on_close(&id, &ctx));
+        self.dispatch_writes(self.clock.now(), || self.fmt_layer.on_close(id, ctx))
     }
 
     fn on_id_change(&self, old: &span::Id, new: &span::Id, ctx: Context<'_, S>) {
@@ -192,7 +380,7 @@ mod tests {
     use chrono::{DateTime, TimeZone, Utc};
     use tracing_subscriber::layer::SubscriberExt;
 
-    use crate::dispatch::LogEvent;
+    use crate::{FieldValue, dispatch::LogEvent};
 
     use super::*;
 
@@ -214,6 +402,7 @@ mod tests {
         }
     }
 
+    #[allow(clippy::inconsistent_digit_grouping)]
     impl std::io::Write for &TestDispatcher {
         fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
             let timestamp: DateTime<Utc> = Utc.timestamp_opt(1_5000_000_000, 0).unwrap();
@@ -223,7 +412,7 @@ mod tests {
             self.events
                 .lock()
                 .unwrap()
-                .push(LogEvent { timestamp, message });
+                .push(LogEvent::new(message, timestamp));
 
             Ok(buf.len())
         }
@@ -262,6 +451,223 @@ mod tests {
         assert_eq!(dispatched.message, "ERROR Error\n");
     }
 
+    /// Dispatcher joining the writes of each event like [`CloudWatchDispatcher`].
+    #[derive(Default)]
+    struct CapturingDispatcher {
+        events: Mutex<Vec<LogEvent>>,
+    }
+
+    impl Dispatcher for CapturingDispatcher {
+        fn dispatch(&self, input: LogEvent) {
+            self.events.lock().unwrap().push(input)
+        }
+    }
+
+    impl std::io::Write for &CapturingDispatcher {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            dispatch::write_event(*self, buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn timestamps_events_with_clock() {
+        let timestamp = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let dispatcher = Arc::new(CapturingDispatcher::default());
+        let subscriber = tracing_subscriber::registry().with(
+            CloudWatchLayer::new(dispatcher.clone())
+                .with_code_location(false)
+                .with_clock(move || timestamp),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(timestamp = 1_000, "not overridden by default");
+        });
+
+        let events = dispatcher.events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].timestamp, timestamp);
+    }
+
+    #[test]
+    fn timestamps_events_from_field() {
+        let dispatcher = Arc::new(CapturingDispatcher::default());
+        let subscriber = tracing_subscriber::registry().with(
+            CloudWatchLayer::new(dispatcher.clone())
+                .with_code_location(false)
+                .with_clock(|| DateTime::<Utc>::UNIX_EPOCH)
+                .with_timestamp_from_field(true),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(timestamp = 1_000, "millis");
+            tracing::info!(timestamp = "2024-01-02T03:04:05Z", "rfc3339");
+            tracing::info!(timestamp = "yesterday", "invalid");
+            tracing::info!("missing");
+        });
+
+        let timestamps: Vec<_> = dispatcher
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| event.timestamp)
+            .collect();
+        assert_eq!(
+            timestamps,
+            [
+                Utc.timestamp_millis_opt(1_000).unwrap(),
+                Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap(),
+                DateTime::<Utc>::UNIX_EPOCH,
+                DateTime::<Utc>::UNIX_EPOCH,
+            ]
+        );
+    }
+
+    #[test]
+    fn exports_span_records() {
+        let dispatcher = Arc::new(CapturingDispatcher::default());
+        let subscriber = tracing_subscriber::registry().with(
+            CloudWatchLayer::new(dispatcher.clone())
+                .with_code_location(false)
+                .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            let parent = tracing::info_span!("parent");
+            let _parent = parent.enter();
+            let child = tracing::info_span!("child", user = "a", status = tracing::field::Empty);
+            child.in_scope(|| tracing::info!("working"));
+            child.record("status", 200);
+        });
+
+        let records: Vec<serde_json::Value> = dispatcher
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|event| serde_json::from_str(&event.message).ok())
+            .collect();
+        let summary: Vec<_> = records
+            .iter()
+            .map(|record| (record["span_event"].as_str(), record["name"].as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (Some("new"), Some("parent")),
+                (Some("new"), Some("child")),
+                (Some("close"), Some("child")),
+                (Some("close"), Some("parent")),
+            ]
+        );
+
+        let child_new = &records[1];
+        let child_close = &records[2];
+        assert_eq!(child_new["parent_id"], records[0]["span_id"]);
+        assert_eq!(child_new["fields"], serde_json::json!({ "user": "a" }));
+        assert!(child_new.get("duration_ns").is_none());
+        assert_eq!(child_close["span_id"], child_new["span_id"]);
+        assert_eq!(
+            child_close["fields"],
+            serde_json::json!({ "user": "a", "status": 200 })
+        );
+        for key in ["busy_ns", "idle_ns", "duration_ns"] {
+            assert!(child_close[key].is_u64(), "{key} missing: {child_close}");
+        }
+        assert!(records[0]["parent_id"].is_null());
+    }
+
+    #[test]
+    fn defers_formatting() {
+        let dispatcher = Arc::new(CapturingDispatcher::default());
+        let subscriber = tracing_subscriber::registry().with(
+            CloudWatchLayer::new(dispatcher.clone()).with_deferred_formatting(
+                crate::JsonFormatter::default().with_code_location(false),
+            ),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request", id = 1, status = tracing::field::Empty);
+            let _span = span.enter();
+            span.record("status", "done");
+            tracing::info!(user = "a", ok = true, "Hello!");
+            tracing::warn!(?span, "outside");
+        });
+
+        let events = dispatcher.events.lock().unwrap();
+        let first: serde_json::Value = serde_json::from_str(&events[0].message).unwrap();
+        assert_eq!(
+            first,
+            serde_json::json!({
+                "level": "INFO",
+                "fields": { "message": "Hello!", "user": "a", "ok": true },
+                "spans": [{ "name": "request", "id": 1, "status": "done" }],
+            })
+        );
+        let second: serde_json::Value = serde_json::from_str(&events[1].message).unwrap();
+        assert_eq!(second["level"], "WARN");
+        assert!(second["fields"]["span"].is_string());
+    }
+
+    #[test]
+    fn records_fields_and_spans_of_formatted_events() {
+        let dispatcher = Arc::new(CapturingDispatcher::default());
+        let subscriber = tracing_subscriber::registry()
+            .with(CloudWatchLayer::new(dispatcher.clone()).with_structured_events(true));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request", id = 1, status = tracing::field::Empty);
+            let _span = span.enter();
+            span.record("status", "done");
+            tracing::info!(user = "a", "Hello!");
+        });
+
+        let events = dispatcher.events.lock().unwrap();
+        let event = &events[0];
+        assert!(event.message.contains("Hello!"), "{}", event.message);
+        assert_eq!(event.level(), Some(tracing::Level::INFO));
+        assert_eq!(
+            event.fields(),
+            [
+                ("message", FieldValue::Debug("Hello!".to_string())),
+                ("user", FieldValue::Str("a".to_string())),
+            ]
+        );
+        let [span] = event.spans() else {
+            panic!("expected one span: {:?}", event.spans());
+        };
+        assert_eq!(span.name(), "request");
+        assert_eq!(
+            span.fields(),
+            [
+                ("id", FieldValue::I64(1)),
+                ("status", FieldValue::Str("done".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn formatted_events_carry_no_record_by_default() {
+        let dispatcher = Arc::new(CapturingDispatcher::default());
+        let subscriber =
+            tracing_subscriber::registry().with(CloudWatchLayer::new(dispatcher.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("request", id = 1)
+                .in_scope(|| tracing::info!(user = "a", "Hello!"));
+        });
+
+        let events = dispatcher.events.lock().unwrap();
+        assert_eq!(events[0].level(), Some(tracing::Level::INFO));
+        assert!(events[0].record().is_none());
+        assert!(events[0].spans().is_empty());
+    }
+
     #[test]
     fn with_fmt_layer_json() {
         let dispatcher = Arc::new(TestDispatcher::new());
@@ -1,6 +1,7 @@
 //!  tracing-cloudwatch is a custom tracing-subscriber layer that sends your application's tracing events(logs) to AWS CloudWatch Logs.
 //!
 //! We have supported [rusoto](https://github.com/rusoto/rusoto) and the [AWS SDK](https://github.com/awslabs/aws-sdk-rust) as AWS clients.
+//! With the `sigv4` feature, a lightweight client calling the CloudWatch Logs API directly is also available.
 //!
 //! ## Usage
 //!
@@ -67,29 +68,145 @@
 //! # }
 //! ```
 //!
+//! ### With SigV4 Client
+//!
+//! feature `sigv4` required
+//!
+//! `SigV4Client` signs requests itself and resolves credentials from env vars, the shared credentials file,
+//! and the container or instance metadata endpoints.
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "sigv4")]
+//! # {
+//! use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let cw_client = tracing_cloudwatch::SigV4Client::new("ap-northeast-1");
+//!
+//!     let (cw_layer, cw_guard) = tracing_cloudwatch::layer().with_client(
+//!         cw_client,
+//!         tracing_cloudwatch::ExportConfig::default()
+//!             .with_log_group_name("tracing-cloudwatch")
+//!             .with_log_stream_name("stream-1"),
+//!     );
+//!
+//!     tracing_subscriber::registry::Registry::default()
+//!         .with(cw_layer)
+//!         .init();
+//!
+//!     cw_guard.shutdown().await;
+//! }
+//! # }
+//! ```
+//!
+//! ### From Environment Variables
+//!
+//! feature `config` required
+//!
+//! `init_from_env` builds a type-erased layer from `TRACING_CLOUDWATCH_*` variables, so log shipping
+//! can be changed without a rebuild. `CloudWatchConfig` can also be deserialized from config files with serde.
+//!
+//! ```rust,no_run
+//! # #[cfg(all(feature = "config", feature = "awssdk"))]
+//! # {
+//! use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+//!     let cw_client = aws_sdk_cloudwatchlogs::Client::new(&config);
+//!
+//!     let (cw_layer, cw_guard) = tracing_cloudwatch::init_from_env(cw_client).unwrap();
+//!
+//!     tracing_subscriber::registry::Registry::default()
+//!         .with(cw_layer)
+//!         .init();
+//!
+//!     cw_guard.shutdown().await;
+//! }
+//! # }
+//! ```
+//!
 //! ## Required Permissions
 //!
 //! Currently, following AWS IAM Permissions required
 //!
 //! * `logs:PutLogEvents`
+//! * `logs:CreateLogGroup` and `logs:CreateLogStream` for [`ExportConfig::validate`] to create a missing log group or log stream
 //!
 //! ## CloudWatch Log Groups and Streams
 //!
-//! This crate does not create a log group and log stream, so if the specified log group and log stream does not exist, it will raise an error.
+//! The exporter does not create a log group and log stream, so if the specified log group and log stream does not exist, it will raise an error.
+//! [`ExportConfig::validate`] creates them ahead of time when the role is allowed to.
 //!
 //! ## Retry and Timeout
 //!
-//! We haven't implemented any custom retry logic or timeout settings within the crate. We assume that these configurations are handled through the SDK Client.
+//! Any client can be wrapped with the layers in [`middleware`] using [`CloudWatchClientExt::with`].
+//! The last layer wraps the others, so here each retry attempt has its own timeout.
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use tracing_cloudwatch::{CloudWatchClient, CloudWatchClientExt, middleware};
+//!
+//! fn with_retry(client: impl CloudWatchClient) -> impl CloudWatchClient {
+//!     client
+//!         .with(middleware::Timeout::new(Duration::from_secs(10)))
+//!         .with(middleware::Retry::new(3))
+//! }
+//! ```
+//!
+//! [`middleware::RateLimit`], [`middleware::CircuitBreaker`] and [`middleware::Metrics`] are also available,
+//! and custom layers can be written by implementing [`ClientLayer`].
+//! Alternatively, the SDK clients can be configured with their own settings.
 //! For instance, in the AWS SDK, you can set up these configurations using [`timeout_config`](https://docs.rs/aws-sdk-cloudwatchlogs/0.28.0/aws_sdk_cloudwatchlogs/config/struct.Builder.html#method.timeout_config) and [`retry_config`](https://docs.rs/aws-sdk-cloudwatchlogs/0.28.0/aws_sdk_cloudwatchlogs/config/struct.Builder.html#method.retry_config)
 
+mod batch;
 mod client;
+mod clock;
+#[cfg(feature = "config")]
+mod config;
+mod dead_letter;
+mod deferred;
 mod dispatch;
 mod export;
 mod guard;
 mod layer;
+#[cfg(any(feature = "sigv4", feature = "resource_detector"))]
+mod metadata;
+mod oversized;
+mod queue;
+mod reload;
+mod resource;
+mod shedding;
+mod span;
+mod writer;
 
-pub use client::CloudWatchClient;
-pub use dispatch::{CloudWatchDispatcher, NoopDispatcher};
-pub use export::{ExportConfig, LogDestination};
-pub use guard::CloudWatchWorkerGuard;
+pub use batch::{AdaptivePolicy, BatchPolicy, FlushTrigger, PendingBatch, ThresholdPolicy};
+pub use client::fallback::{FallbackClient, LocalSink, RotatingFile};
+pub use client::middleware::{self, ClientLayer, CloudWatchClientExt};
+#[cfg(feature = "sigv4")]
+pub use client::sigv4::{
+    Credentials, CredentialsError, CredentialsProvider, InvalidRegion, SigV4Client,
+};
+pub use client::tee::{TeeClient, TeeError, TeePolicy};
+pub use client::{BatchMeta, BoxFuture, CloudWatchClient, LogBatch, PutLogsError};
+pub use clock::{Clock, SystemClock};
+#[cfg(feature = "config")]
+pub use config::{BoxedLayer, CloudWatchConfig, FormatKind, LayerConfig, init_from_env};
+pub use dead_letter::{DeadLetter, DeadLetterSink, JsonlDeadLetter, ReingestError};
+pub use deferred::{EventFormatter, EventRecord, FieldValue, JsonFormatter, SpanContext};
+pub use dispatch::{CloudWatchDispatcher, LogEvent, NoopDispatcher};
+pub use export::{
+    BatchExporter, ConfigError, ExportConfig, ExportError, ExporterTask, LogDestination,
+    PreflightError,
+};
+pub use guard::{CloudWatchWorkerGuard, WorkerHealth};
 pub use layer::{CloudWatchLayer, layer};
+pub use oversized::OversizedEventPolicy;
+pub use reload::{ReloadError, ReloadHandle};
+#[cfg(feature = "resource_detector")]
+pub use resource::ResourceDetector;
+pub use shedding::LoadShedding;
+pub use writer::{CloudWatchMakeWriter, CloudWatchWriter};
@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use hyper::{Body, Method, Request, client::HttpConnector};
+use hyper_rustls::HttpsConnector;
+
+/// The default instance metadata service endpoint.
+pub(crate) const IMDS_ENDPOINT: &str = "http://169.254.169.254";
+/// Metadata endpoints are not reachable outside AWS, so give up on them quickly.
+const METADATA_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub(crate) type HttpClient = hyper::Client<HttpsConnector<HttpConnector>>;
+
+pub(crate) fn http_client() -> HttpClient {
+    let connector = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .https_or_http()
+        .enable_http1()
+        .build();
+    hyper::Client::builder().build(connector)
+}
+
+/// Errors raised while calling the container or instance metadata endpoints.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum MetadataError {
+    #[error("{0}")]
+    Http(String),
+    #[error("{0}")]
+    InvalidResponse(String),
+}
+
+/// Fetch an IMDSv2 session token.
+pub(crate) async fn imds_token(http: &HttpClient, base: &str) -> Result<String, MetadataError> {
+    let request = Request::builder()
+        .method(Method::PUT)
+        .uri(format!("{base}/latest/api/token"))
+        .header("x-aws-ec2-metadata-token-ttl-seconds", "21600")
+        .body(Body::empty());
+    let body = send(http, request).await?;
+    String::from_utf8(body).map_err(|err| MetadataError::InvalidResponse(err.to_string()))
+}
+
+/// GET an instance metadata path with an IMDSv2 session token.
+pub(crate) async fn imds_get(
+    http: &HttpClient,
+    url: &str,
+    token: &str,
+) -> Result<String, MetadataError> {
+    let request = Request::get(url)
+        .header("x-aws-ec2-metadata-token", token)
+        .body(Body::empty());
+    let body = send(http, request).await?;
+    String::from_utf8(body).map_err(|err| MetadataError::InvalidResponse(err.to_string()))
+}
+
+pub(crate) async fn send(
+    http: &HttpClient,
+    request: Result<Request<Body>, hyper::http::Error>,
+) -> Result<Vec<u8>, MetadataError> {
+    let request = request.map_err(|err| MetadataError::Http(err.to_string()))?;
+    let response = tokio::time::timeout(METADATA_TIMEOUT, http.request(request))
+        .await
+        .map_err(|_| MetadataError::Http("timed out".to_string()))?
+        .map_err(|err| MetadataError::Http(err.to_string()))?;
+
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body())
+        .await
+        .map_err(|err| MetadataError::Http(err.to_string()))?;
+    if !status.is_success() {
+        return Err(MetadataError::Http(format!("status {status}")));
+    }
+    Ok(body.to_vec())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[derive(Debug, Clone)]
+    pub(crate) struct RecordedRequest {
+        pub(crate) method: String,
+        pub(crate) path: String,
+        pub(crate) headers: Vec<(String, String)>,
+        pub(crate) body: String,
+    }
+
+    impl RecordedRequest {
+        pub(crate) fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        }
+    }
+
+    /// A local HTTP server answering requests with canned `(status, body)` responses in order.
+    pub(crate) struct StandIn {
+        addr: std::net::SocketAddr,
+        requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    }
+
+    impl StandIn {
+        pub(crate) async fn start(responses: Vec<(u16, String)>) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let requests = Arc::new(Mutex::new(Vec::new()));
+
+            let recorded = requests.clone();
+            tokio::spawn(async move {
+                for (status, body) in responses {
+                    let Ok((mut stream, _)) = listener.accept().await else {
+                        return;
+                    };
+                    let request = read_request(&mut stream).await;
+                    recorded.lock().unwrap().push(request);
+                    let response = format!(
+                        "HTTP/1.1 {status} OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    let _ = stream.shutdown().await;
+                }
+            });
+
+            Self { addr, requests }
+        }
+
+        pub(crate) fn url(&self) -> String {
+            format!("http://{}", self.addr)
+        }
+
+        pub(crate) fn requests(&self) -> Vec<RecordedRequest> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    async fn read_request(stream: &mut tokio::net::TcpStream) -> RecordedRequest {
+        let mut buf = Vec::new();
+        let mut chunk = [0; 4096];
+        let header_end = loop {
+            let read = stream.read(&mut chunk).await.unwrap();
+            buf.extend_from_slice(&chunk[..read]);
+            if let Some(pos) = buf.windows(4).position(|window| window == b"\r\n\r\n") {
+                break pos + 4;
+            }
+            if read == 0 {
+                break buf.len();
+            }
+        };
+
+        let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+        let mut lines = head.lines();
+        let mut request_line = lines.next().unwrap_or_default().split(' ');
+        let method = request_line.next().unwrap_or_default().to_string();
+        let path = request_line.next().unwrap_or_default().to_string();
+        let headers: Vec<(String, String)> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect();
+
+        let content_length = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| value.parse::<usize>().ok())
+            .unwrap_or(0);
+        while buf.len() < header_end + content_length {
+            let read = stream.read(&mut chunk).await.unwrap();
+            if read == 0 {
+                break;
+            }
+            buf.extend_from_slice(&chunk[..read]);
+        }
+
+        RecordedRequest {
+            method,
+            path,
+            headers,
+            body: String::from_utf8_lossy(&buf[header_end..]).to_string(),
+        }
+    }
+}
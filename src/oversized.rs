@@ -0,0 +1,212 @@
+use crate::dispatch::LogEvent;
+
+/// The maximum size of a single CloudWatch log event in bytes.
+pub(crate) const MAX_EVENT_SIZE: usize = 256 * 1024;
+
+/// CloudWatch adds 26 bytes of overhead to every event when computing its size.
+pub(crate) const EVENT_OVERHEAD: usize = 26;
+
+/// The maximum number of UTF-8 bytes a message can hold before CloudWatch rejects it.
+pub(crate) const MAX_MESSAGE_BYTES: usize = MAX_EVENT_SIZE - EVENT_OVERHEAD;
+
+/// Marker appended to truncated messages.
+const TRUNCATED_MARKER: &str = "...[truncated]";
+
+/// How to handle an event whose message exceeds the CloudWatch event size limit.
+///
+/// Sizes are measured in UTF-8 bytes and messages are never cut in the middle of a character.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum OversizedEventPolicy {
+    /// Truncate the message and append a `...[truncated]` marker.
+    #[default]
+    Truncate,
+    /// Split the message into numbered continuation events sharing a correlation ID.
+    ///
+    /// Each part is prefixed with `[split:<id> <n>/<total>] `.
+    Split,
+    /// Drop the event and count it.
+    Drop,
+}
+
+pub(crate) struct OversizedEventHandler {
+    policy: OversizedEventPolicy,
+    max_message_bytes: usize,
+    split_seq: u64,
+    dropped: u64,
+}
+
+impl OversizedEventHandler {
+    pub(crate) fn new(policy: OversizedEventPolicy) -> Self {
+        Self::with_max_message_bytes(policy, MAX_MESSAGE_BYTES)
+    }
+
+    fn with_max_message_bytes(policy: OversizedEventPolicy, max_message_bytes: usize) -> Self {
+        Self {
+            policy,
+            max_message_bytes,
+            split_seq: 0,
+            dropped: 0,
+        }
+    }
+
+    pub(crate) fn set_policy(&mut self, policy: OversizedEventPolicy) {
+        self.policy = policy;
+    }
+
+    /// Push the event into the queue, applying the policy if it is oversized.
+    /// Returns the size of the event if it was dropped.
+    pub(crate) fn push(&mut self, event: LogEvent, queue: &mut Vec<LogEvent>) -> Option<usize> {
+        if event.message.len() <= self.max_message_bytes {
+            queue.push(event);
+            return None;
+        }
+
+        match self.policy {
+            OversizedEventPolicy::Truncate => queue.push(self.truncate(event)),
+            OversizedEventPolicy::Split => self.split(event, queue),
+            OversizedEventPolicy::Drop => {
+                self.dropped += 1;
+                return Some(event.message.len());
+            }
+        }
+        None
+    }
+
+    /// The number of oversized events dropped so far.
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    fn truncate(&self, mut event: LogEvent) -> LogEvent {
+        let budget = self
+            .max_message_bytes
+            .saturating_sub(TRUNCATED_MARKER.len());
+        let end = floor_char_boundary(&event.message, budget);
+        event.message.truncate(end);
+        event.message.push_str(TRUNCATED_MARKER);
+        event
+    }
+
+    fn split(&mut self, event: LogEvent, queue: &mut Vec<LogEvent>) {
+        self.split_seq += 1;
+        let id = format!(
+            "{:x}-{:x}",
+            event.timestamp.timestamp_millis(),
+            self.split_seq
+        );
+
+        // The number of parts can never exceed the number of bytes, so reserving
+        // that many digits keeps every prefix within the budget.
+        let digits = event.message.len().to_string().len();
+        let prefix_len = format!("[split:{id} {0:>digits$}/{0:>digits$}] ", 0).len();
+        let budget = self.max_message_bytes.saturating_sub(prefix_len).max(4);
+
+        let mut parts = Vec::new();
+        let mut rest = event.message.as_str();
+        while !rest.is_empty() {
+            let mut end = floor_char_boundary(rest, budget);
+            if end == 0 {
+                // Always make progress, even if the budget is smaller than a character.
+                end = rest.chars().next().map_or(rest.len(), char::len_utf8);
+            }
+            let (part, tail) = rest.split_at(end);
+            parts.push(part);
+            rest = tail;
+        }
+
+        let total = parts.len();
+        queue.extend(parts.into_iter().enumerate().map(|(idx, part)| {
+            event.with_message(format!("[split:{id} {}/{total}] {part}", idx + 1))
+        }));
+    }
+}
+
+/// Find the largest index not greater than `index` that lies on a char boundary.
+fn floor_char_boundary(s: &str, index: usize) -> usize {
+    if index >= s.len() {
+        return s.len();
+    }
+    let mut index = index;
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn event(message: &str) -> LogEvent {
+        LogEvent::new(message, Utc::now())
+    }
+
+    fn push(policy: OversizedEventPolicy, max: usize, message: &str) -> Vec<LogEvent> {
+        let mut handler = OversizedEventHandler::with_max_message_bytes(policy, max);
+        let mut queue = Vec::new();
+        handler.push(event(message), &mut queue);
+        queue
+    }
+
+    #[test]
+    fn passes_through_events_within_limit() {
+        for policy in [
+            OversizedEventPolicy::Truncate,
+            OversizedEventPolicy::Split,
+            OversizedEventPolicy::Drop,
+        ] {
+            let queue = push(policy, 16, "0123456789abcdef");
+            assert_eq!(queue.len(), 1);
+            assert_eq!(queue[0].message, "0123456789abcdef");
+        }
+    }
+
+    #[test]
+    fn truncates_on_char_boundary() {
+        // "é" is 2 bytes, so a 5 byte budget must not cut the third character.
+        let max = TRUNCATED_MARKER.len() + 5;
+        let queue = push(OversizedEventPolicy::Truncate, max, &"é".repeat(20));
+
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue[0].message, format!("éé{TRUNCATED_MARKER}"));
+        assert!(queue[0].message.len() <= max);
+    }
+
+    #[test]
+    fn splits_into_numbered_parts() {
+        let message = "あいうえおかきくけこ".repeat(10);
+        let queue = push(OversizedEventPolicy::Split, 64, &message);
+
+        assert!(queue.len() > 1);
+        let id = queue[0].message[..queue[0].message.find(' ').unwrap()].to_string();
+        let total = queue.len();
+
+        let mut reassembled = String::new();
+        for (idx, part) in queue.iter().enumerate() {
+            assert!(part.message.len() <= 64, "part exceeds limit: {part:?}");
+            let prefix = format!("{id} {}/{total}] ", idx + 1);
+            assert!(part.message.starts_with(&prefix), "{part:?}");
+            reassembled.push_str(&part.message[prefix.len()..]);
+        }
+        assert_eq!(reassembled, message);
+    }
+
+    #[test]
+    fn drops_and_counts() {
+        let mut handler =
+            OversizedEventHandler::with_max_message_bytes(OversizedEventPolicy::Drop, 4);
+        let mut queue = Vec::new();
+        handler.push(event("too long"), &mut queue);
+        handler.push(event("ok"), &mut queue);
+        handler.push(event("also too long"), &mut queue);
+
+        assert_eq!(queue.len(), 1);
+        assert_eq!(handler.dropped, 2);
+    }
+}
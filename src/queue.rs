@@ -0,0 +1,285 @@
+use std::sync::{
+    Arc, OnceLock,
+    atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+use crossbeam_queue::ArrayQueue;
+use tokio::sync::Notify;
+use tracing_core::Level;
+
+use crate::{
+    deferred::{EventFormatter, EventRecord},
+    dispatch::LogEvent,
+    shedding::{LoadShedding, Shedder},
+};
+
+/// Number of message buffers kept for reuse.
+const POOL_SIZE: usize = 1024;
+
+/// Buffers which grew larger than this are freed instead of being reused,
+/// so a burst of large events does not pin memory forever.
+const MAX_POOLED_CAPACITY: usize = 16 * 1024;
+
+static MESSAGE_POOL: OnceLock<ArrayQueue<String>> = OnceLock::new();
+
+fn message_pool() -> &'static ArrayQueue<String> {
+    MESSAGE_POOL.get_or_init(|| ArrayQueue::new(POOL_SIZE))
+}
+
+/// Take an empty message buffer, reusing the allocation of an exported event if possible.
+pub(crate) fn take_message_buffer() -> String {
+    message_pool().pop().unwrap_or_default()
+}
+
+/// Return the message of an exported event so its allocation is reused by a later event.
+pub(crate) fn recycle_message(mut message: String) {
+    if message.capacity() == 0 || message.capacity() > MAX_POOLED_CAPACITY {
+        return;
+    }
+    message.clear();
+    let _ = message_pool().push(message);
+}
+
+/// Event waiting in the queue, formatted by the exporter if it was captured with deferred formatting.
+enum QueuedEvent {
+    Formatted(LogEvent),
+    Deferred(EventRecord, Arc<dyn EventFormatter>),
+}
+
+impl QueuedEvent {
+    fn level(&self) -> Option<Level> {
+        match self {
+            Self::Formatted(event) => event.level(),
+            Self::Deferred(record, _) => Some(*record.level()),
+        }
+    }
+
+    fn recycle(self) {
+        if let Self::Formatted(event) = self {
+            recycle_message(event.message);
+        }
+    }
+
+    fn into_event(self) -> LogEvent {
+        match self {
+            Self::Formatted(event) => event,
+            Self::Deferred(record, formatter) => LogEvent::encode(record, formatter.as_ref()),
+        }
+    }
+}
+
+struct Shared {
+    events: ArrayQueue<QueuedEvent>,
+    notify: Notify,
+    closed: AtomicBool,
+    dropped: AtomicU64,
+    shedder: Option<Shedder>,
+}
+
+/// Create a bounded lock-free queue handing events from the application threads to the exporter.
+pub(crate) fn event_queue(
+    capacity: usize,
+    shedding: Option<LoadShedding>,
+) -> (EventSender, EventReceiver) {
+    let shared = Arc::new(Shared {
+        events: ArrayQueue::new(capacity),
+        notify: Notify::new(),
+        closed: AtomicBool::new(false),
+        dropped: AtomicU64::new(0),
+        shedder: shedding.map(|shedding| Shedder::new(shedding, capacity)),
+    });
+    (EventSender(shared.clone()), EventReceiver(shared))
+}
+
+/// Sending half of [`event_queue`]. The receiver is closed when it is dropped.
+pub(crate) struct EventSender(Arc<Shared>);
+
+impl EventSender {
+    /// Enqueue the event without blocking, or drop it if the queue is full.
+    pub(crate) fn send(&self, event: LogEvent) {
+        self.push(QueuedEvent::Formatted(event));
+    }
+
+    /// Enqueue a captured event to be formatted by the exporter.
+    pub(crate) fn send_deferred(&self, record: EventRecord, formatter: Arc<dyn EventFormatter>) {
+        self.push(QueuedEvent::Deferred(record, formatter));
+    }
+
+    /// Identify the queue, shared by the receiving exporter.
+    pub(crate) fn id(&self) -> usize {
+        Arc::as_ptr(&self.0) as usize
+    }
+
+    fn push(&self, event: QueuedEvent) {
+        if let Some(shedder) = &self.0.shedder
+            && shedder.shed(event.level(), self.0.events.len())
+        {
+            return event.recycle();
+        }
+        match self.0.events.push(event) {
+            Ok(()) => self.0.notify.notify_one(),
+            Err(event) => {
+                self.0.dropped.fetch_add(1, Ordering::Relaxed);
+                event.recycle();
+            }
+        }
+    }
+}
+
+impl Drop for EventSender {
+    fn drop(&mut self) {
+        self.0.closed.store(true, Ordering::Release);
+        self.0.notify.notify_one();
+    }
+}
+
+/// Receiving half of [`event_queue`].
+pub(crate) struct EventReceiver(Arc<Shared>);
+
+impl EventReceiver {
+    /// Wait for the next event. Returns `None` once the sender is dropped and the queue is empty.
+    /// Events captured with deferred formatting are formatted here, on the exporter task.
+    ///
+    /// Cancel safe: no event is lost if the future is dropped.
+    pub(crate) async fn recv(&mut self) -> Option<LogEvent> {
+        loop {
+            if let Some(event) = self.try_recv() {
+                return Some(event);
+            }
+            if self.0.closed.load(Ordering::Acquire) {
+                // The sender may have pushed right before closing.
+                return self.try_recv();
+            }
+            self.0.notify.notified().await;
+        }
+    }
+
+    pub(crate) fn try_recv(&mut self) -> Option<LogEvent> {
+        self.0.events.pop().map(QueuedEvent::into_event)
+    }
+
+    /// Number of events dropped because the queue was full since the last call.
+    pub(crate) fn take_dropped(&self) -> u64 {
+        self.0.dropped.swap(0, Ordering::Relaxed)
+    }
+
+    /// Number of TRACE, DEBUG and INFO events shed by [`LoadShedding`] since the last call.
+    pub(crate) fn take_shed(&self) -> [u64; 3] {
+        self.0
+            .shedder
+            .as_ref()
+            .map(Shedder::take_shed)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dispatch::tests::{DEBUG, ERROR, INFO, TRACE};
+    use chrono::Utc;
+
+    fn event(message: &str) -> LogEvent {
+        LogEvent::new(message, Utc::now())
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn drops_events_when_full() {
+        let (tx, mut rx) = event_queue(2, None);
+
+        for message in ["1", "2", "3"] {
+            tx.send(event(message));
+        }
+        drop(tx);
+
+        assert_eq!(rx.take_dropped(), 1);
+        assert_eq!(rx.recv().await.unwrap().message, "1");
+        assert_eq!(rx.recv().await.unwrap().message, "2");
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn sheds_events_above_watermarks() {
+        let shedding = LoadShedding::new()
+            .with_trace_watermark(25)
+            .with_debug_watermark(50)
+            .with_info_watermark(75);
+        let (tx, mut rx) = event_queue(4, Some(shedding));
+
+        for (message, metadata) in [
+            ("1", &INFO),
+            ("trace", &TRACE),
+            ("2", &DEBUG),
+            ("debug", &DEBUG),
+            ("3", &INFO),
+            ("info", &INFO),
+            ("4", &ERROR),
+        ] {
+            tx.send(event(message).with_metadata(metadata));
+        }
+
+        assert_eq!(rx.take_shed(), [1, 1, 1]);
+        assert_eq!(rx.take_dropped(), 0);
+        for expected in ["1", "2", "3", "4"] {
+            assert_eq!(rx.try_recv().unwrap().message, expected);
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn wakes_receiver() {
+        let (tx, mut rx) = event_queue(2, None);
+
+        let recv = tokio::spawn(async move { rx.recv().await.map(|event| event.message) });
+        tokio::task::yield_now().await;
+        tx.send(event("wake"));
+
+        assert_eq!(recv.await.unwrap().as_deref(), Some("wake"));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn formats_deferred_events_on_receive() {
+        use std::sync::atomic::AtomicUsize;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        static FORMATTED: AtomicUsize = AtomicUsize::new(0);
+        let formatter = |record: &EventRecord, buf: &mut String| {
+            FORMATTED.fetch_add(1, Ordering::Relaxed);
+            buf.push_str(record.level().as_str());
+        };
+        let (tx, mut rx) = event_queue(2, None);
+        let dispatcher = Arc::new(DeferringDispatcher(tx));
+        let subscriber = tracing_subscriber::registry()
+            .with(crate::CloudWatchLayer::new(dispatcher).with_deferred_formatting(formatter));
+
+        tracing::subscriber::with_default(subscriber, || tracing::info!("deferred"));
+
+        assert_eq!(FORMATTED.load(Ordering::Relaxed), 0);
+        let received = rx.recv().await.unwrap();
+        assert_eq!(received.message, "INFO");
+        assert_eq!(received.level(), Some(Level::INFO));
+        assert_eq!(received.fields()[0].0, "message");
+        assert_eq!(FORMATTED.load(Ordering::Relaxed), 1);
+    }
+
+    struct DeferringDispatcher(EventSender);
+
+    impl crate::dispatch::Dispatcher for DeferringDispatcher {
+        fn dispatch(&self, event: LogEvent) {
+            self.0.send(event);
+        }
+
+        fn dispatch_deferred(&self, record: EventRecord, formatter: &Arc<dyn EventFormatter>) {
+            self.0.send_deferred(record, formatter.clone());
+        }
+    }
+
+    impl std::io::Write for &DeferringDispatcher {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+}
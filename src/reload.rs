@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+use crate::export::{ConfigError, ExportConfig};
+
+/// Errors raised by [`ReloadHandle`].
+#[derive(Debug, thiserror::Error)]
+pub enum ReloadError {
+    #[error("the exporter has already stopped")]
+    WorkerStopped,
+    /// The new configuration was rejected, the exporter keeps the current one.
+    #[error(transparent)]
+    InvalidConfig(#[from] ConfigError),
+}
+
+/// Handle to change the [`ExportConfig`] of a running exporter.
+///
+/// Changes are applied by the worker between flushes: logs buffered before a reload are
+/// flushed with the previous configuration, and no buffered logs are lost.
+/// Obtained from [`CloudWatchWorkerGuard::reload_handle`](crate::CloudWatchWorkerGuard::reload_handle).
+///
+/// Formatter options live in the layer, so they are changed with
+/// [`tracing_subscriber::reload`] by rebuilding the layer around the same dispatcher:
+///
+/// ```rust
+/// use tracing_cloudwatch::{CloudWatchDispatcher, CloudWatchLayer};
+/// use tracing_subscriber::{Registry, reload};
+///
+/// type Handle = reload::Handle<CloudWatchLayer<Registry, CloudWatchDispatcher>, Registry>;
+///
+/// fn disable_code_location(handle: &Handle) {
+///     handle
+///         .modify(|layer| {
+///             *layer = CloudWatchLayer::new(layer.dispatcher()).with_code_location(false)
+///         })
+///         .unwrap();
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReloadHandle {
+    tx: Arc<watch::Sender<ExportConfig>>,
+}
+
+impl ReloadHandle {
+    pub(crate) fn new(config: ExportConfig) -> (Self, watch::Receiver<ExportConfig>) {
+        let (tx, rx) = watch::channel(config);
+        (Self { tx: Arc::new(tx) }, rx)
+    }
+
+    /// Return the configuration currently requested for the exporter.
+    pub fn current(&self) -> ExportConfig {
+        self.tx.borrow().clone()
+    }
+
+    /// Replace the configuration of the exporter.
+    pub fn reload(&self, config: ExportConfig) -> Result<(), ReloadError> {
+        self.modify(|current| *current = config)
+    }
+
+    /// Modify the configuration of the exporter in place.
+    ///
+    /// The modified configuration is checked like [`ExportConfig::try_build`] and only applied if
+    /// it is valid. The queue capacity of a running exporter does not change.
+    ///
+    /// ```rust,no_run
+    /// # fn run(handle: tracing_cloudwatch::ReloadHandle) {
+    /// handle
+    ///     .modify(|config| {
+    ///         *config = config
+    ///             .clone()
+    ///             .with_log_stream_name("debug-stream")
+    ///             .with_interval(std::time::Duration::from_secs(1))
+    ///     })
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn modify(&self, f: impl FnOnce(&mut ExportConfig)) -> Result<(), ReloadError> {
+        if self.tx.is_closed() {
+            return Err(ReloadError::WorkerStopped);
+        }
+        let mut result = Ok(());
+        self.tx.send_if_modified(|current| {
+            let mut config = current.clone();
+            f(&mut config);
+            match config.check() {
+                Ok(()) => {
+                    *current = config;
+                    true
+                }
+                Err(err) => {
+                    result = Err(err.into());
+                    false
+                }
+            }
+        });
+        result
+    }
+}
@@ -0,0 +1,352 @@
+use std::{collections::BTreeMap, fmt::Write as _};
+
+use serde_json::Value;
+
+/// The resource fields of [`ExportConfig::with_resource`](crate::ExportConfig::with_resource),
+/// escaped once to be added to every event message.
+#[derive(Debug, Default)]
+pub(crate) struct Resource {
+    /// Each key as written between the quotes of a JSON key, and its `"key":"value"` member.
+    members: Vec<(String, String)>,
+    /// The ` key=value` pairs appended to text messages.
+    text: String,
+}
+
+impl Resource {
+    pub(crate) fn new(fields: &BTreeMap<String, String>) -> Self {
+        let mut resource = Self::default();
+        for (key, value) in fields {
+            let json_key = Value::from(key.as_str()).to_string();
+            let member = format!("{json_key}:{}", Value::from(value.as_str()));
+            resource
+                .members
+                .push((json_key[1..json_key.len() - 1].to_string(), member));
+
+            if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"') {
+                let _ = write!(resource.text, " {key}={value:?}");
+            } else {
+                let _ = write!(resource.text, " {key}={value}");
+            }
+        }
+        resource
+    }
+
+    /// Add the fields to an event message.
+    ///
+    /// JSON objects get the fields as top-level keys, without overriding fields of the event.
+    /// Other messages get ` key=value` pairs appended to the line.
+    pub(crate) fn enrich(&self, message: &mut String) {
+        if self.members.is_empty() {
+            return;
+        }
+
+        let line_end = message.trim_end().len();
+        let newline = message.split_off(line_end);
+
+        if let Some(keys) = object_keys(message) {
+            let mut members = String::new();
+            for (_, member) in self
+                .members
+                .iter()
+                .filter(|(key, _)| !keys.contains(&key.as_str()))
+            {
+                if !keys.is_empty() || !members.is_empty() {
+                    members.push(',');
+                }
+                members.push_str(member);
+            }
+            message.pop();
+            message.push_str(&members);
+            message.push('}');
+        } else {
+            message.push_str(&self.text);
+        }
+        message.push_str(&newline);
+    }
+}
+
+/// The top-level keys of a JSON object as written between their quotes, found without parsing
+/// the values. `None` if `json` is not an object.
+fn object_keys(json: &str) -> Option<Vec<&str>> {
+    let bytes = json.as_bytes();
+    if bytes.first() != Some(&b'{') || bytes.last() != Some(&b'}') {
+        return None;
+    }
+
+    let mut keys = Vec::new();
+    let mut depth = 0_usize;
+    let mut expect_key = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                let start = i + 1;
+                i = start;
+                loop {
+                    match bytes.get(i)? {
+                        b'\\' => i += 2,
+                        b'"' => break,
+                        _ => i += 1,
+                    }
+                }
+                if depth == 1 && expect_key {
+                    keys.push(&json[start..i]);
+                    expect_key = false;
+                }
+            }
+            open @ (b'{' | b'[') => {
+                depth += 1;
+                expect_key = open == b'{' && depth == 1;
+            }
+            b'}' | b']' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 && i != bytes.len() - 1 {
+                    return None;
+                }
+            }
+            b',' if depth == 1 => expect_key = true,
+            byte if expect_key && !byte.is_ascii_whitespace() => return None,
+            _ => {}
+        }
+        i += 1;
+    }
+    (depth == 0).then_some(keys)
+}
+
+#[cfg(feature = "resource_detector")]
+pub use detector::ResourceDetector;
+
+#[cfg(feature = "resource_detector")]
+mod detector {
+    use std::collections::BTreeMap;
+
+    use hyper::{Body, Request};
+    use serde_json::Value;
+
+    use crate::metadata::{self, HttpClient, IMDS_ENDPOINT, http_client, imds_get, imds_token};
+
+    /// Detect resource fields describing where the process runs, to be added to every event with
+    /// [`ExportConfig::with_resource`](crate::ExportConfig::with_resource).
+    ///
+    /// feature `resource_detector` required
+    ///
+    /// | Field | Source |
+    /// | --- | --- |
+    /// | `host.name` | `/proc/sys/kernel/hostname`, `/etc/hostname` or `HOSTNAME` |
+    /// | `aws.ecs.task.arn` | ECS task metadata endpoint from `ECS_CONTAINER_METADATA_URI_V4` |
+    /// | `host.id` | EC2 instance ID from IMDSv2, skipped in Lambda or if `AWS_EC2_METADATA_DISABLED=true` |
+    /// | `faas.name` | `AWS_LAMBDA_FUNCTION_NAME` |
+    ///
+    /// Sources which are not available are skipped.
+    ///
+    /// ```rust,no_run
+    /// # async fn run() {
+    /// let resource = tracing_cloudwatch::ResourceDetector::new().detect().await;
+    /// let config = tracing_cloudwatch::ExportConfig::default()
+    ///     .with_resource(resource)
+    ///     .with_resource_field("service", "checkout");
+    /// # let _ = config;
+    /// # }
+    /// ```
+    #[derive(Debug, Clone)]
+    pub struct ResourceDetector {
+        ecs_metadata_endpoint: Option<String>,
+        imds_endpoint: String,
+    }
+
+    impl Default for ResourceDetector {
+        fn default() -> Self {
+            Self {
+                ecs_metadata_endpoint: None,
+                imds_endpoint: IMDS_ENDPOINT.to_string(),
+            }
+        }
+    }
+
+    impl ResourceDetector {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Set the ECS task metadata endpoint.
+        /// Default `ECS_CONTAINER_METADATA_URI_V4`.
+        pub fn with_ecs_metadata_endpoint(self, ecs_metadata_endpoint: impl Into<String>) -> Self {
+            Self {
+                ecs_metadata_endpoint: Some(ecs_metadata_endpoint.into()),
+                ..self
+            }
+        }
+
+        /// Set the instance metadata service endpoint.
+        /// Default `http://169.254.169.254`.
+        pub fn with_imds_endpoint(self, imds_endpoint: impl Into<String>) -> Self {
+            Self {
+                imds_endpoint: imds_endpoint.into(),
+                ..self
+            }
+        }
+
+        pub async fn detect(&self) -> BTreeMap<String, String> {
+            self.detect_with(&http_client(), |name| std::env::var(name).ok())
+                .await
+        }
+
+        async fn detect_with(
+            &self,
+            http: &HttpClient,
+            env: impl Fn(&str) -> Option<String>,
+        ) -> BTreeMap<String, String> {
+            let mut resource = BTreeMap::new();
+
+            if let Some(hostname) = hostname(&env) {
+                resource.insert("host.name".to_string(), hostname);
+            }
+
+            let ecs_endpoint = self
+                .ecs_metadata_endpoint
+                .clone()
+                .or_else(|| env("ECS_CONTAINER_METADATA_URI_V4"));
+            if let Some(task_arn) = match ecs_endpoint {
+                Some(endpoint) => ecs_task_arn(http, &endpoint).await,
+                None => None,
+            } {
+                resource.insert("aws.ecs.task.arn".to_string(), task_arn);
+            }
+
+            let function_name = env("AWS_LAMBDA_FUNCTION_NAME");
+            let imds_disabled = env("AWS_EC2_METADATA_DISABLED")
+                .is_some_and(|value| value.eq_ignore_ascii_case("true"));
+            if function_name.is_none()
+                && !imds_disabled
+                && let Some(instance_id) = self.instance_id(http).await
+            {
+                resource.insert("host.id".to_string(), instance_id);
+            }
+
+            if let Some(function_name) = function_name {
+                resource.insert("faas.name".to_string(), function_name);
+            }
+
+            resource
+        }
+
+        async fn instance_id(&self, http: &HttpClient) -> Option<String> {
+            let base = &self.imds_endpoint;
+            let token = imds_token(http, base).await.ok()?;
+            let url = format!("{base}/latest/meta-data/instance-id");
+            imds_get(http, &url, &token)
+                .await
+                .ok()
+                .filter(|instance_id| !instance_id.is_empty())
+        }
+    }
+
+    async fn ecs_task_arn(http: &HttpClient, endpoint: &str) -> Option<String> {
+        let request = Request::get(format!("{endpoint}/task")).body(Body::empty());
+        let body = metadata::send(http, request).await.ok()?;
+        let task: Value = serde_json::from_slice(&body).ok()?;
+        task["TaskARN"].as_str().map(str::to_string)
+    }
+
+    fn hostname(env: impl Fn(&str) -> Option<String>) -> Option<String> {
+        ["/proc/sys/kernel/hostname", "/etc/hostname"]
+            .into_iter()
+            .find_map(|path| std::fs::read_to_string(path).ok())
+            .or_else(|| env("HOSTNAME"))
+            .map(|hostname| hostname.trim().to_string())
+            .filter(|hostname| !hostname.is_empty())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::metadata::tests::StandIn;
+
+        #[tokio::test]
+        async fn detects_ecs_ec2_and_lambda() {
+            let ecs = StandIn::start(vec![(
+                200,
+                r#"{"TaskARN":"arn:aws:ecs:us-east-1:123:task/cluster/abc"}"#.to_string(),
+            )])
+            .await;
+            let imds = StandIn::start(vec![
+                (200, "token".to_string()),
+                (200, "i-0123456789".to_string()),
+            ])
+            .await;
+            let detector = ResourceDetector::new()
+                .with_ecs_metadata_endpoint(ecs.url())
+                .with_imds_endpoint(imds.url());
+
+            let resource = detector.detect_with(&http_client(), |_| None).await;
+
+            assert_eq!(
+                resource["aws.ecs.task.arn"],
+                "arn:aws:ecs:us-east-1:123:task/cluster/abc"
+            );
+            assert_eq!(resource["host.id"], "i-0123456789");
+            assert_eq!(ecs.requests()[0].path, "/task");
+            assert!(ecs.requests()[0].body.is_empty());
+            assert_eq!(imds.requests()[0].method, "PUT");
+            assert_eq!(
+                imds.requests()[1].header("x-aws-ec2-metadata-token"),
+                Some("token")
+            );
+
+            let resource = detector
+                .detect_with(&http_client(), |name| {
+                    (name == "AWS_LAMBDA_FUNCTION_NAME").then(|| "handler".to_string())
+                })
+                .await;
+            assert_eq!(resource["faas.name"], "handler");
+            assert!(!resource.contains_key("host.id"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resource() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("service".to_string(), "checkout".to_string()),
+            ("env".to_string(), "prod eu".to_string()),
+        ])
+    }
+
+    #[test]
+    fn enriches_json_and_text() {
+        let resource = Resource::new(&resource());
+
+        let mut json = "{\"message\":\"hi\",\"service\":\"own\"}\n".to_string();
+        resource.enrich(&mut json);
+        assert_eq!(
+            json,
+            "{\"message\":\"hi\",\"service\":\"own\",\"env\":\"prod eu\"}\n"
+        );
+
+        let mut empty = "{}".to_string();
+        resource.enrich(&mut empty);
+        assert_eq!(empty, "{\"env\":\"prod eu\",\"service\":\"checkout\"}");
+
+        let mut text = " INFO hi\n".to_string();
+        resource.enrich(&mut text);
+        assert_eq!(text, " INFO hi env=\"prod eu\" service=checkout\n");
+
+        let mut braces = "{not json}".to_string();
+        resource.enrich(&mut braces);
+        assert_eq!(braces, "{not json} env=\"prod eu\" service=checkout");
+    }
+
+    #[test]
+    fn finds_top_level_keys_only() {
+        assert_eq!(
+            object_keys(r#"{"a":{"b":1,"c":[{"d":2}]},"e\"f":"}","g":[1,2]}"#),
+            Some(vec!["a", r#"e\"f"#, "g"])
+        );
+        assert_eq!(object_keys("{ }"), Some(vec![]));
+        assert_eq!(object_keys(r#"{"a":1}{"b":2}"#), None);
+        assert_eq!(object_keys(r#"{"a":"unterminated}"#), None);
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tracing_core::Level;
+
+/// Drop low-priority events while the exporter falls behind, set with
+/// [`ExportConfig::with_load_shedding`](crate::ExportConfig::with_load_shedding).
+///
+/// Once the queue holds more events than a watermark, given in percent of the queue capacity,
+/// new events of that level are dropped: TRACE first, then DEBUG, then INFO. WARN and ERROR events
+/// are kept until the queue is full. Events without a level, such as span lifecycle records,
+/// are shed like INFO events.
+///
+/// The shed events are counted and reported to the error handler as
+/// [`ExportError::EventsShed`](crate::ExportError::EventsShed).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct LoadShedding {
+    trace_percent: u8,
+    debug_percent: u8,
+    info_percent: u8,
+}
+
+impl Default for LoadShedding {
+    fn default() -> Self {
+        Self {
+            trace_percent: 50,
+            debug_percent: 70,
+            info_percent: 85,
+        }
+    }
+}
+
+impl LoadShedding {
+    /// Shed TRACE events at 50%, DEBUG events at 70% and INFO events at 85% of the queue capacity.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the watermark of TRACE events in percent of the queue capacity. Default 50.
+    pub fn with_trace_watermark(self, percent: u8) -> Self {
+        Self {
+            trace_percent: percent,
+            ..self
+        }
+    }
+
+    /// Set the watermark of DEBUG events in percent of the queue capacity. Default 70.
+    pub fn with_debug_watermark(self, percent: u8) -> Self {
+        Self {
+            debug_percent: percent,
+            ..self
+        }
+    }
+
+    /// Set the watermark of INFO events in percent of the queue capacity. Default 85.
+    pub fn with_info_watermark(self, percent: u8) -> Self {
+        Self {
+            info_percent: percent,
+            ..self
+        }
+    }
+
+    /// Whether the watermarks are at most 100 and do not shed a level before a less severe one.
+    pub(crate) fn is_valid(&self) -> bool {
+        self.trace_percent <= self.debug_percent
+            && self.debug_percent <= self.info_percent
+            && self.info_percent <= 100
+    }
+}
+
+/// Applies [`LoadShedding`] to a queue, counting the shed events.
+pub(crate) struct Shedder {
+    /// Queue lengths from which TRACE, DEBUG and INFO events are shed.
+    limits: [usize; 3],
+    shed: [AtomicU64; 3],
+}
+
+impl Shedder {
+    pub(crate) fn new(shedding: LoadShedding, capacity: usize) -> Self {
+        let limit = |percent: u8| capacity * usize::from(percent) / 100;
+        Self {
+            limits: [
+                limit(shedding.trace_percent),
+                limit(shedding.debug_percent),
+                limit(shedding.info_percent),
+            ],
+            shed: Default::default(),
+        }
+    }
+
+    /// Whether an event of `level` is dropped while `queued` events are waiting.
+    pub(crate) fn shed(&self, level: Option<Level>, queued: usize) -> bool {
+        let index = match level {
+            Some(level) if level == Level::TRACE => 0,
+            Some(level) if level == Level::DEBUG => 1,
+            Some(level) if level == Level::INFO => 2,
+            None => 2,
+            Some(_) => return false,
+        };
+        if queued < self.limits[index] {
+            return false;
+        }
+        self.shed[index].fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// The number of TRACE, DEBUG and INFO events shed since the last call.
+    pub(crate) fn take_shed(&self) -> [u64; 3] {
+        self.shed
+            .each_ref()
+            .map(|shed| shed.swap(0, Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sheds_less_severe_levels_first() {
+        let shedder = Shedder::new(LoadShedding::default(), 100);
+
+        assert!(!shedder.shed(Some(Level::TRACE), 49));
+        assert!(shedder.shed(Some(Level::TRACE), 50));
+        assert!(!shedder.shed(Some(Level::DEBUG), 50));
+        assert!(shedder.shed(Some(Level::DEBUG), 70));
+        assert!(!shedder.shed(Some(Level::INFO), 84));
+        assert!(shedder.shed(None, 85));
+        assert!(!shedder.shed(Some(Level::WARN), 99));
+        assert!(!shedder.shed(Some(Level::ERROR), 99));
+
+        assert_eq!(shedder.take_shed(), [1, 1, 1]);
+        assert_eq!(shedder.take_shed(), [0, 0, 0]);
+    }
+
+    #[test]
+    fn validates_watermarks() {
+        assert!(LoadShedding::default().is_valid());
+        assert!(!LoadShedding::default().with_trace_watermark(80).is_valid());
+        assert!(!LoadShedding::default().with_info_watermark(101).is_valid());
+    }
+}
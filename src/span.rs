@@ -0,0 +1,207 @@
+use std::time::{Duration, Instant};
+
+use serde_json::{Map, Value};
+use tracing_core::{
+    Field, Subscriber,
+    field::Visit,
+    span::{self, Attributes, Record},
+};
+use tracing_subscriber::{fmt::format::FmtSpan, layer::Context, registry::LookupSpan};
+
+/// Fields and timings of a span, stored in its extensions while span records are enabled.
+struct SpanData {
+    fields: Map<String, Value>,
+    created: Instant,
+    last: Instant,
+    busy: Duration,
+    idle: Duration,
+}
+
+/// Builds structured records of span lifecycle events.
+///
+/// Each record is a JSON object such as
+///
+/// ```json
+/// {"span_event":"close","span_id":2,"parent_id":1,"name":"handle","target":"app","fields":{"user":"a"},"busy_ns":1200,"idle_ns":300,"duration_ns":1500}
+/// ```
+///
+/// `busy_ns`, `idle_ns` and `duration_ns` are only included in `close` records.
+#[derive(Debug)]
+pub(crate) struct SpanRecorder {
+    kind: FmtSpan,
+}
+
+impl SpanRecorder {
+    pub(crate) fn new(kind: FmtSpan) -> Self {
+        Self { kind }
+    }
+
+    fn enabled(&self, kind: FmtSpan) -> bool {
+        self.kind.clone() & kind.clone() == kind
+    }
+
+    fn any_enabled(&self) -> bool {
+        self.kind != FmtSpan::NONE
+    }
+
+    pub(crate) fn on_new_span<S>(
+        &self,
+        attrs: &Attributes<'_>,
+        id: &span::Id,
+        ctx: &Context<'_, S>,
+    ) -> Option<String>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        if !self.any_enabled() {
+            return None;
+        }
+        let span = ctx.span(id)?;
+
+        let mut fields = Map::new();
+        attrs.record(&mut JsonVisitor(&mut fields));
+        let now = Instant::now();
+        span.extensions_mut().insert(SpanData {
+            fields,
+            created: now,
+            last: now,
+            busy: Duration::ZERO,
+            idle: Duration::ZERO,
+        });
+
+        self.enabled(FmtSpan::NEW)
+            .then(|| self.record("new", id, ctx, false))
+            .flatten()
+    }
+
+    pub(crate) fn on_record<S>(&self, id: &span::Id, values: &Record<'_>, ctx: &Context<'_, S>)
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if let Some(data) = span.extensions_mut().get_mut::<SpanData>() {
+            values.record(&mut JsonVisitor(&mut data.fields));
+        }
+    }
+
+    pub(crate) fn on_enter<S>(&self, id: &span::Id, ctx: &Context<'_, S>) -> Option<String>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        self.update_timings(id, ctx, |data, elapsed| data.idle += elapsed);
+        self.enabled(FmtSpan::ENTER)
+            .then(|| self.record("enter", id, ctx, false))
+            .flatten()
+    }
+
+    pub(crate) fn on_exit<S>(&self, id: &span::Id, ctx: &Context<'_, S>) -> Option<String>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        self.update_timings(id, ctx, |data, elapsed| data.busy += elapsed);
+        self.enabled(FmtSpan::EXIT)
+            .then(|| self.record("exit", id, ctx, false))
+            .flatten()
+    }
+
+    pub(crate) fn on_close<S>(&self, id: &span::Id, ctx: &Context<'_, S>) -> Option<String>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        self.update_timings(id, ctx, |data, elapsed| data.idle += elapsed);
+        self.enabled(FmtSpan::CLOSE)
+            .then(|| self.record("close", id, ctx, true))
+            .flatten()
+    }
+
+    fn update_timings<S>(
+        &self,
+        id: &span::Id,
+        ctx: &Context<'_, S>,
+        f: impl FnOnce(&mut SpanData, Duration),
+    ) where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if let Some(data) = span.extensions_mut().get_mut::<SpanData>() {
+            let now = Instant::now();
+            let elapsed = now.saturating_duration_since(data.last);
+            data.last = now;
+            f(data, elapsed);
+        }
+    }
+
+    fn record<S>(
+        &self,
+        span_event: &str,
+        id: &span::Id,
+        ctx: &Context<'_, S>,
+        with_timings: bool,
+    ) -> Option<String>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let span = ctx.span(id)?;
+        let extensions = span.extensions();
+        let data = extensions.get::<SpanData>()?;
+
+        let mut record = Map::new();
+        record.insert("span_event".into(), span_event.into());
+        record.insert("span_id".into(), id.into_u64().into());
+        record.insert(
+            "parent_id".into(),
+            span.parent()
+                .map_or(Value::Null, |parent| parent.id().into_u64().into()),
+        );
+        record.insert("name".into(), span.name().into());
+        record.insert("target".into(), span.metadata().target().into());
+        record.insert("fields".into(), Value::Object(data.fields.clone()));
+        if with_timings {
+            record.insert("busy_ns".into(), nanos(data.busy));
+            record.insert("idle_ns".into(), nanos(data.idle));
+            record.insert("duration_ns".into(), nanos(data.created.elapsed()));
+        }
+
+        Some(Value::Object(record).to_string())
+    }
+}
+
+fn nanos(duration: Duration) -> Value {
+    u64::try_from(duration.as_nanos())
+        .unwrap_or(u64::MAX)
+        .into()
+}
+
+/// Record span fields into a JSON object.
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().into(), format!("{value:?}").into());
+    }
+}
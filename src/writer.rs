@@ -0,0 +1,182 @@
+use std::{io, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use tracing_core::{Level, Metadata};
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::{
+    client::CloudWatchClient,
+    dispatch::{CloudWatchDispatcher, Dispatcher, LogEvent},
+    export::{BatchExporter, ExportConfig},
+    guard::CloudWatchWorkerGuard,
+    queue,
+};
+
+/// [`MakeWriter`] exporting to CloudWatch, to be used with any formatter instead of
+/// [`CloudWatchLayer`](crate::CloudWatchLayer).
+///
+/// Each writer it makes is exported as a single CloudWatch event when it is dropped,
+/// however many times it is written to. Formatters such as [`tracing_subscriber::fmt::Layer`]
+/// make one writer per event, so each tracing event maps to exactly one CloudWatch event.
+///
+/// ```rust,no_run
+/// use tracing_subscriber::{filter::LevelFilter, layer::SubscriberExt, util::SubscriberInitExt, Layer};
+///
+/// # async fn run(client: impl tracing_cloudwatch::CloudWatchClient + 'static) {
+/// let (writer, guard) = tracing_cloudwatch::CloudWatchMakeWriter::new(
+///     client,
+///     tracing_cloudwatch::ExportConfig::default()
+///         .with_log_group_name("tracing-cloudwatch")
+///         .with_log_stream_name("stream-1"),
+/// );
+///
+/// tracing_subscriber::registry()
+///     .with(
+///         tracing_subscriber::fmt::layer()
+///             .json()
+///             .with_writer(writer)
+///             .with_filter(LevelFilter::INFO),
+///     )
+///     .init();
+///
+/// guard.shutdown().await;
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct CloudWatchMakeWriter {
+    dispatcher: Arc<CloudWatchDispatcher>,
+}
+
+impl CloudWatchMakeWriter {
+    /// Spawn the exporter sending the written events with `client`. It must be called within a
+    /// tokio runtime.
+    pub fn new<C>(client: C, export_config: ExportConfig) -> (Self, CloudWatchWorkerGuard)
+    where
+        C: CloudWatchClient + Send + Sync + 'static,
+    {
+        let (dispatcher, guard) = BatchExporter::new(client, export_config).spawn();
+        (Self::from_dispatcher(dispatcher), guard)
+    }
+
+    /// Export through `dispatcher`, e.g. of an exporter run with [`BatchExporter::into_task`].
+    pub fn from_dispatcher(dispatcher: CloudWatchDispatcher) -> Self {
+        Self {
+            dispatcher: Arc::new(dispatcher),
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for CloudWatchMakeWriter {
+    type Writer = CloudWatchWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        CloudWatchWriter {
+            dispatcher: &self.dispatcher,
+            buf: queue::take_message_buffer().into_bytes(),
+            timestamp: Utc::now(),
+            level: None,
+        }
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        let mut writer = self.make_writer();
+        writer.level = Some(*meta.level());
+        writer
+    }
+}
+
+/// Writer of a single CloudWatch event, made by [`CloudWatchMakeWriter`].
+///
+/// The event is stamped when the writer is made, and exported when it is dropped,
+/// unless nothing was written.
+pub struct CloudWatchWriter<'a> {
+    dispatcher: &'a CloudWatchDispatcher,
+    buf: Vec<u8>,
+    timestamp: DateTime<Utc>,
+    level: Option<Level>,
+}
+
+impl io::Write for CloudWatchWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for CloudWatchWriter<'_> {
+    fn drop(&mut self) {
+        let buf = std::mem::take(&mut self.buf);
+        let message = String::from_utf8(buf)
+            .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned());
+        if message.is_empty() {
+            return queue::recycle_message(message);
+        }
+        let event = LogEvent::new(message, self.timestamp);
+        self.dispatcher.dispatch(match self.level {
+            Some(level) => event.with_level(level),
+            None => event,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, sync::Mutex};
+
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::client::{BoxFuture, LogBatch, PutLogsError};
+
+    #[derive(Clone, Default)]
+    struct RecordingClient {
+        events: Arc<Mutex<Vec<LogEvent>>>,
+    }
+
+    impl CloudWatchClient for RecordingClient {
+        fn put_logs<'a>(&'a self, batch: LogBatch<'a>) -> BoxFuture<'a, Result<(), PutLogsError>> {
+            self.events.lock().unwrap().extend_from_slice(batch.events);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn exports_one_event_per_writer() {
+        let client = RecordingClient::default();
+        let (writer, guard) = CloudWatchMakeWriter::new(
+            client.clone(),
+            ExportConfig::default()
+                .with_log_group_name("group")
+                .with_log_stream_name("stream"),
+        );
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .json()
+                .without_time()
+                .with_writer(writer.clone()),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(user = "a", "first");
+            tracing::error!("second");
+        });
+        let mut plain = writer.make_writer();
+        write!(plain, "not ").unwrap();
+        write!(plain, "tracing").unwrap();
+        drop(plain);
+        drop(writer.make_writer());
+        guard.shutdown().await;
+
+        let events = client.events.lock().unwrap();
+        assert_eq!(events.len(), 3, "{events:?}");
+        assert!(events[0].message.contains(r#""user":"a""#), "{events:?}");
+        assert_eq!(events[0].level(), Some(Level::INFO));
+        assert_eq!(events[1].level(), Some(Level::ERROR));
+        assert_eq!(events[2].message, "not tracing");
+        assert_eq!(events[2].level(), None);
+    }
+}